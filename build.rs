@@ -46,6 +46,44 @@ struct ColorsConfig {
     colors: HashMap<String, String>,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum ColorUtilityConfig {
+    Property(String),
+    Detailed {
+        property: String,
+        #[serde(default)]
+        selector: Option<String>,
+    },
+}
+
+#[derive(Deserialize, Debug)]
+struct ColorUtilitiesConfig {
+    color_utilities: HashMap<String, ColorUtilityConfig>,
+}
+
+/// One `[composites.<name>]` entry. Token lists are space-separated utilities.
+#[derive(Deserialize, Debug)]
+struct CompositeConfig {
+    #[serde(default)]
+    base: String,
+    #[serde(default)]
+    child: HashMap<String, String>,
+    #[serde(default)]
+    state: HashMap<String, String>,
+    #[serde(default)]
+    data: HashMap<String, String>,
+    #[serde(default)]
+    conditional: HashMap<String, String>,
+    #[serde(default)]
+    raw: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CompositesConfig {
+    composites: HashMap<String, CompositeConfig>,
+}
+
 #[derive(Deserialize, Debug)]
 struct AnimationGeneratorsConfig {
     animation_generators: HashMap<String, String>,
@@ -193,6 +231,13 @@ fn main() {
         .map(|c| c.properties)
         .unwrap_or_default();
     let themes = read_theme_tokens(&style_dir.join("themes.toml"));
+    let color_utilities =
+        read_toml_file::<ColorUtilitiesConfig>(&style_dir.join("color_utilities.toml"))
+            .map(|c| c.color_utilities)
+            .unwrap_or_default();
+    let composites = read_toml_file::<CompositesConfig>(&style_dir.join("composites.toml"))
+        .map(|c| c.composites)
+        .unwrap_or_default();
 
     let mut builder = FlatBufferBuilder::new();
 
@@ -368,6 +413,66 @@ fn main() {
         property_offsets.push(prop_offset);
     }
     let properties_vec = builder.create_vector(&property_offsets);
+    let mut color_utility_offsets = Vec::new();
+    for (prefix, config) in color_utilities {
+        let (property, selector) = match config {
+            ColorUtilityConfig::Property(property) => (property, None),
+            ColorUtilityConfig::Detailed { property, selector } => (property, selector),
+        };
+        let prefix_offset = builder.create_string(&prefix);
+        let property_offset = builder.create_string(&property);
+        let selector_offset = selector.map(|s| builder.create_string(&s));
+        let table_wip = builder.start_table();
+        builder.push_slot(4, prefix_offset, WIPOffset::new(0));
+        builder.push_slot(6, property_offset, WIPOffset::new(0));
+        if let Some(selector_offset) = selector_offset {
+            builder.push_slot(8, selector_offset, WIPOffset::new(0));
+        }
+        let utility_offset = builder.end_table(table_wip);
+        color_utility_offsets.push(utility_offset);
+    }
+    let color_utilities_vec = builder.create_vector(&color_utility_offsets);
+    let mut composite_offsets = Vec::new();
+    for (name, composite) in composites {
+        let name_offset = builder.create_string(&name);
+        let base_offset = builder.create_string(&composite.base);
+        let mut rule_vecs = Vec::new();
+        for rules in [
+            &composite.child,
+            &composite.state,
+            &composite.data,
+            &composite.conditional,
+        ] {
+            let mut rule_offsets = Vec::new();
+            for (key, tokens) in rules {
+                let key_offset = builder.create_string(key);
+                let tokens_offset = builder.create_string(tokens);
+                let table_wip = builder.start_table();
+                builder.push_slot(4, key_offset, WIPOffset::new(0));
+                builder.push_slot(6, tokens_offset, WIPOffset::new(0));
+                rule_offsets.push(builder.end_table(table_wip));
+            }
+            rule_vecs.push(builder.create_vector(&rule_offsets));
+        }
+        let raw_offsets: Vec<_> = composite
+            .raw
+            .iter()
+            .map(|r| builder.create_string(r))
+            .collect();
+        let raw_vec = builder.create_vector(&raw_offsets);
+        let table_wip = builder.start_table();
+        builder.push_slot(4, name_offset, WIPOffset::new(0));
+        builder.push_slot(6, base_offset, WIPOffset::new(0));
+        // Slots 8 to 14: child, state, data, conditional.
+        builder.push_slot(8, rule_vecs[0], WIPOffset::new(0));
+        builder.push_slot(10, rule_vecs[1], WIPOffset::new(0));
+        builder.push_slot(12, rule_vecs[2], WIPOffset::new(0));
+        builder.push_slot(14, rule_vecs[3], WIPOffset::new(0));
+        builder.push_slot(16, raw_vec, WIPOffset::new(0));
+        let composite_offset = builder.end_table(table_wip);
+        composite_offsets.push(composite_offset);
+    }
+    let composites_vec = builder.create_vector(&composite_offsets);
     let base_css = fs::read_to_string(style_dir.join("base.css")).unwrap_or_default();
     let base_css_offset = builder.create_string(&base_css);
     let property_css = fs::read_to_string(style_dir.join("property.css")).unwrap_or_default();
@@ -386,6 +491,9 @@ fn main() {
     builder.push_slot(22, themes_vec, WIPOffset::new(0));
     builder.push_slot(24, base_css_offset, WIPOffset::new(0));
     builder.push_slot(26, property_css_offset, WIPOffset::new(0));
+    // Slots 28 and 30 are not in style.fbs; the engine reads them as raw tables.
+    builder.push_slot(28, color_utilities_vec, WIPOffset::new(0));
+    builder.push_slot(30, composites_vec, WIPOffset::new(0));
     let config_root = builder.end_table(table_wip);
 
     builder.finish(config_root, None);
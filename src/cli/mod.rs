@@ -0,0 +1,366 @@
+//! Command-line parsing for the `dx-style` binary.
+//!
+//! Every flag maps onto a `Config` field and overrides whatever `.dx/config.toml` says.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::config::{Config, WatchConfig};
+
+pub const DEFAULT_CONFIG_PATH: &str = ".dx/config.toml";
+
+pub const USAGE: &str = "\
+Usage: dx-style [command] [flags]
+
+Commands:
+  watch          Build once, then rebuild on every change (default)
+  build          Build once and exit; exits non-zero on error
+  check          Exit non-zero if the CSS file is not what `build` would write
+  clean          Remove the cache directory
+  print-config   Print the effective configuration as TOML
+  theme from-image <image>
+                 Print the theme seed color scored best in an image
+  theme audit    Report theme role colors that look alike with color vision deficiencies
+
+Flags:
+  -c, --config <path>      Config file (default: .dx/config.toml)
+      --index <file>       Override paths.index_file
+      --css <file>         Override paths.css_file
+      --html-dir <dir>     Override paths.html_dir
+      --style-dir <dir>    Override paths.style_dir
+      --cache-dir <dir>    Override paths.cache_dir
+      --content <glob>     Replace content globs (repeatable)
+      --debounce-ms <ms>   Override watch.debounce_ms
+      --no-format          Disable the delayed formatter (format.interval_ms = 0)
+      --strict             Fail `build`/`check` on any diagnostic (diagnostics.strict)
+      --candidates <n>     With `theme from-image`, list the top n seed colors
+      --min-distance <n>   With `theme audit`, the CAM16-UCS distance pairs need (default 8)
+  -h, --help               Print this help
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Watch,
+    Build,
+    Check,
+    Clean,
+    PrintConfig,
+    ThemeFromImage,
+    ThemeAudit,
+    Help,
+}
+
+impl Command {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "watch" => Some(Command::Watch),
+            "build" => Some(Command::Build),
+            "check" => Some(Command::Check),
+            "clean" => Some(Command::Clean),
+            "print-config" => Some(Command::PrintConfig),
+            "help" => Some(Command::Help),
+            _ => None,
+        }
+    }
+}
+
+/// Config fields set from the command line. `None` (or an empty list) leaves the file's value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Overrides {
+    pub index_file: Option<String>,
+    pub css_file: Option<String>,
+    pub html_dir: Option<String>,
+    pub style_dir: Option<String>,
+    pub cache_dir: Option<String>,
+    pub content: Vec<String>,
+    pub debounce_ms: Option<u64>,
+    pub no_format: bool,
+    pub strict: bool,
+}
+
+impl Overrides {
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(v) = &self.index_file {
+            config.paths.index_file = v.clone();
+        }
+        if let Some(v) = &self.css_file {
+            config.paths.css_file = v.clone();
+        }
+        if let Some(v) = &self.html_dir {
+            config.paths.html_dir = v.clone();
+        }
+        if let Some(v) = &self.style_dir {
+            config.paths.style_dir = Some(v.clone());
+        }
+        if let Some(v) = &self.cache_dir {
+            config.paths.cache_dir = Some(v.clone());
+        }
+        if !self.content.is_empty() {
+            config.content = self.content.clone();
+        }
+        if let Some(ms) = self.debounce_ms {
+            config
+                .watch
+                .get_or_insert_with(WatchConfig::default)
+                .debounce_ms = Some(ms);
+        }
+        if self.no_format {
+            let mut format = config.format.clone().unwrap_or_default();
+            format.interval_ms = 0;
+            config.format = Some(format);
+        }
+        if self.strict {
+            config.diagnostics.strict = true;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cli {
+    pub command: Command,
+    /// Set only when `--config` was given; a missing explicit file is an error.
+    pub config_path: Option<String>,
+    pub overrides: Overrides,
+    /// Image given to `theme from-image`.
+    pub image: Option<String>,
+    /// Number of seed colors `theme from-image` lists; one when unset.
+    pub candidates: Option<usize>,
+    /// Distance below which `theme audit` reports a pair.
+    pub min_distance: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliError(String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl Cli {
+    pub fn from_env() -> Result<Self, CliError> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse<I, S>(args: I) -> Result<Self, CliError>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut command = None;
+        let mut config_path = None;
+        let mut overrides = Overrides::default();
+        let mut image = None;
+        let mut candidates = None;
+        let mut min_distance = None;
+        let mut args = args.into_iter().map(Into::into);
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if arg.starts_with("--") => (flag.to_string(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+            let inline = inline.map(str::to_string);
+            let mut value = || -> Result<String, CliError> {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| CliError(format!("{} needs a value", flag)))
+            };
+            match flag.as_str() {
+                "-h" | "--help" => command = Some(Command::Help),
+                "-c" | "--config" => config_path = Some(value()?),
+                "--index" => overrides.index_file = Some(value()?),
+                "--css" => overrides.css_file = Some(value()?),
+                "--html-dir" => overrides.html_dir = Some(value()?),
+                "--style-dir" => overrides.style_dir = Some(value()?),
+                "--cache-dir" => overrides.cache_dir = Some(value()?),
+                "--content" => overrides.content.push(value()?),
+                "--debounce-ms" => {
+                    let raw = value()?;
+                    let ms = raw.parse::<u64>().map_err(|_| {
+                        CliError(format!("--debounce-ms expects milliseconds, got `{}`", raw))
+                    })?;
+                    overrides.debounce_ms = Some(ms);
+                }
+                "--no-format" => overrides.no_format = true,
+                "--strict" => overrides.strict = true,
+                "--candidates" => {
+                    let raw = value()?;
+                    let n = raw
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| {
+                            CliError(format!(
+                                "--candidates expects a positive count, got `{}`",
+                                raw
+                            ))
+                        })?;
+                    candidates = Some(n);
+                }
+                "--min-distance" => {
+                    let raw = value()?;
+                    let distance = raw
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|d| d.is_finite() && *d > 0.0)
+                        .ok_or_else(|| {
+                            CliError(format!(
+                                "--min-distance expects a positive number, got `{}`",
+                                raw
+                            ))
+                        })?;
+                    min_distance = Some(distance);
+                }
+                other if other.starts_with('-') => {
+                    return Err(CliError(format!("unknown flag `{}`", other)));
+                }
+                "theme" if command.is_none() => match args.next().as_deref() {
+                    Some("from-image") => {
+                        let path = args.next().ok_or_else(|| {
+                            CliError("theme from-image needs an image path".into())
+                        })?;
+                        image = Some(path);
+                        command = Some(Command::ThemeFromImage);
+                    }
+                    Some("audit") => command = Some(Command::ThemeAudit),
+                    Some(other) => {
+                        return Err(CliError(format!("unknown theme command `{}`", other)));
+                    }
+                    None => {
+                        return Err(CliError(
+                            "theme needs a command: from-image or audit".into(),
+                        ));
+                    }
+                },
+                other => {
+                    let parsed = Command::parse(other)
+                        .ok_or_else(|| CliError(format!("unknown command `{}`", other)))?;
+                    if command.is_some_and(|c| c != Command::Help) {
+                        return Err(CliError(format!("unexpected argument `{}`", other)));
+                    }
+                    command.get_or_insert(parsed);
+                }
+            }
+        }
+        Ok(Self {
+            command: command.unwrap_or(Command::Watch),
+            config_path,
+            overrides,
+            image,
+            candidates,
+            min_distance,
+        })
+    }
+
+    /// Loads the config file, then applies `DX_*` environment overrides and finally the
+    /// command-line flags. Without `--config` a missing `.dx/config.toml` falls back to the
+    /// defaults, as before; any other error reading or validating it is returned.
+    pub fn load_config(&self) -> Result<Config, Box<dyn std::error::Error>> {
+        let mut config = match &self.config_path {
+            Some(path) => Config::load_from(path)?,
+            None => load_optional(DEFAULT_CONFIG_PATH)?,
+        };
+        config.apply_env_overrides();
+        self.overrides.apply(&mut config);
+        Ok(config)
+    }
+}
+
+/// Loads `path`, or the defaults when it does not exist.
+fn load_optional(path: &str) -> Result<Config, Box<dyn std::error::Error>> {
+    if Path::new(path).exists() {
+        Config::load_from(path)
+    } else {
+        Ok(Config::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subcommand_and_flags_override_config() {
+        let cli = Cli::parse([
+            "build",
+            "--css=dist/app.css",
+            "--content",
+            "src/**/*.tsx",
+            "--content",
+            "app/**/*.vue",
+            "--debounce-ms",
+            "40",
+            "--no-format",
+        ])
+        .unwrap();
+        assert_eq!(cli.command, Command::Build);
+        let mut config = Config::default();
+        cli.overrides.apply(&mut config);
+        assert_eq!(config.paths.css_file, "dist/app.css");
+        assert_eq!(config.content, vec!["src/**/*.tsx", "app/**/*.vue"]);
+        assert_eq!(config.watch.and_then(|w| w.debounce_ms), Some(40));
+        assert_eq!(config.format.map(|f| f.interval_ms), Some(0));
+    }
+
+    #[test]
+    fn defaults_to_watch_and_rejects_bad_input() {
+        let empty: [&str; 0] = [];
+        assert_eq!(Cli::parse(empty).unwrap().command, Command::Watch);
+        assert_eq!(Cli::parse(["-h"]).unwrap().command, Command::Help);
+        assert!(Cli::parse(["deploy"]).is_err());
+        assert!(Cli::parse(["build", "check"]).is_err());
+        assert!(Cli::parse(["--debounce-ms", "soon"]).is_err());
+        assert!(Cli::parse(["--css"]).is_err());
+    }
+
+    #[test]
+    fn theme_from_image_takes_a_path_and_candidate_count() {
+        let cli =
+            Cli::parse(["theme", "from-image", "brand/logo.png", "--candidates", "5"]).unwrap();
+        assert_eq!(cli.command, Command::ThemeFromImage);
+        assert_eq!(cli.image.as_deref(), Some("brand/logo.png"));
+        assert_eq!(cli.candidates, Some(5));
+        assert!(Cli::parse(["theme"]).is_err());
+        assert!(Cli::parse(["theme", "from-image"]).is_err());
+        assert!(Cli::parse(["theme", "to-image", "x.png"]).is_err());
+        assert!(Cli::parse(["theme", "from-image", "x.png", "--candidates", "0"]).is_err());
+    }
+
+    #[test]
+    fn theme_audit_takes_a_min_distance() {
+        let cli = Cli::parse(["theme", "audit", "--min-distance", "12.5"]).unwrap();
+        assert_eq!(cli.command, Command::ThemeAudit);
+        assert_eq!(cli.min_distance, Some(12.5));
+        assert_eq!(Cli::parse(["theme", "audit"]).unwrap().min_distance, None);
+        assert!(Cli::parse(["theme", "audit", "--min-distance", "-1"]).is_err());
+    }
+
+    #[test]
+    fn only_a_missing_default_config_falls_back() {
+        let dir = std::env::temp_dir().join(format!("dx_style_cli_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let path_str = path.to_string_lossy().into_owned();
+        assert!(load_optional(&path_str).is_ok());
+
+        let paths = "[paths]\nhtml_dir = \"site\"\nindex_file = \"site/index.html\"\ncss_file = \"site/app.css\"\n";
+        std::fs::write(&path, paths).unwrap();
+        assert_eq!(
+            load_optional(&path_str).unwrap().paths.css_file,
+            "site/app.css"
+        );
+        for broken in [
+            "[paths\n",
+            "[theme]\nvariant = \"loud\"\n",
+            "[theme]\nsource = \"teal-ish\"\n",
+        ] {
+            std::fs::write(&path, format!("{}{}", paths, broken)).unwrap();
+            assert!(load_optional(&path_str).is_err(), "{}", broken);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
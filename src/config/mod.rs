@@ -1,7 +1,9 @@
-use serde::Deserialize;
+use crate::core::color::{color::Argb, dynamic_color::Variant};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct PathsConfig {
     pub html_dir: String,
     pub index_file: String,
@@ -12,20 +14,374 @@ pub struct PathsConfig {
     pub cache_dir: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct WatchConfig {
     pub debounce_ms: Option<u64>,
+    /// Poll the index file at this interval instead of using native file events.
+    #[serde(default)]
+    pub poll_ms: Option<u64>,
+    /// Rebuild on every raw file event, bypassing the debouncer.
+    #[serde(default)]
+    pub raw: bool,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
     pub paths: PathsConfig,
     pub watch: Option<WatchConfig>,
     #[serde(default)]
     pub format: Option<FormatConfig>,
+    #[serde(default)]
+    pub content: Vec<String>,
+    #[serde(default)]
+    pub grouping: GroupingConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub parser: ParserConfig,
+    #[serde(default)]
+    pub diagnostics: DiagnosticsConfig,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct GroupingConfig {
+    /// Minimum Jaccard similarity for an edited group to keep its cached CSS under a new alias.
+    #[serde(default = "GroupingConfig::default_rename_similarity")]
+    pub rename_similarity: f64,
+    /// Rewrite class attributes that overlap a group's utilities into the group alias.
+    #[serde(default)]
+    pub aggressive_rewrite: bool,
+    /// Share of a class attribute that must belong to a group before it is rewritten.
+    #[serde(default = "GroupingConfig::default_utility_overlap")]
+    pub utility_overlap: f64,
+    /// Replace a group's plain utility list with its alias wherever it appears verbatim.
+    #[serde(default)]
+    pub rewrite_plain_alias: bool,
+}
+
+impl GroupingConfig {
+    fn default_rename_similarity() -> f64 {
+        0.6
+    }
+    fn default_utility_overlap() -> f64 {
+        0.5
+    }
+}
+
+impl Default for GroupingConfig {
+    fn default() -> Self {
+        GroupingConfig {
+            rename_similarity: GroupingConfig::default_rename_similarity(),
+            aggressive_rewrite: false,
+            utility_overlap: GroupingConfig::default_utility_overlap(),
+            rewrite_plain_alias: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct OutputConfig {
+    /// CSS files at least this large are written through a memory map.
+    #[serde(default = "OutputConfig::default_mmap_threshold")]
+    pub mmap_threshold: u64,
+    #[serde(default = "OutputConfig::default_flush_interval")]
+    pub flush_interval_ms: u64,
+    /// Rewrite the whole CSS file on every rebuild, even when nothing changed.
+    #[serde(default)]
+    pub force_full: bool,
+}
+
+impl OutputConfig {
+    fn default_mmap_threshold() -> u64 {
+        64 * 1024
+    }
+    fn default_flush_interval() -> u64 {
+        25
+    }
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            mmap_threshold: OutputConfig::default_mmap_threshold(),
+            flush_interval_ms: OutputConfig::default_flush_interval(),
+            force_full: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ParserConfig {
+    /// Re-parse only the changed regions of the index file.
+    #[serde(default = "ParserConfig::default_incremental")]
+    pub incremental: bool,
+    /// Log incremental parser statistics and grouping decisions to stderr.
+    #[serde(default)]
+    pub debug: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DiagnosticsConfig {
+    /// Report classes that generate no CSS, with a did-you-mean suggestion.
+    #[serde(default = "DiagnosticsConfig::default_enabled")]
+    pub enabled: bool,
+    /// Make `build` and `check` fail when any diagnostic is found.
+    #[serde(default)]
+    pub strict: bool,
+    /// Check text colors against the background set on the same element. Off by default, so
+    /// `strict` only fails on contrast when the audit was asked for.
+    #[serde(default)]
+    pub contrast: bool,
+    /// WCAG contrast ratio a text/background pair needs; 4.5 is level AA for body text.
+    #[serde(default = "DiagnosticsConfig::default_min_contrast_ratio")]
+    pub min_contrast_ratio: f64,
+    /// APCA lightness contrast (absolute Lc) a text/background pair needs.
+    #[serde(default = "DiagnosticsConfig::default_min_apca_lc")]
+    pub min_apca_lc: f64,
+}
+
+/// How the dark variable block and the `dark:`/`light:` variants are selected.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DarkMode {
+    /// A `.dark` class on an ancestor.
+    #[default]
+    Class,
+    /// The `prefers-color-scheme` media feature.
+    Media,
+    /// A `data-mode="dark"` attribute on an ancestor.
+    DataAttribute,
+    /// Either the selector or the media feature.
+    Both,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct ThemeConfig {
+    #[serde(default)]
+    pub dark_mode: DarkMode,
+    /// Replaces `.dark` / `[data-mode="dark"]` as the dark selector, e.g. `[data-mode="night"]`.
+    #[serde(default)]
+    pub dark_selector: Option<String>,
+    /// `themes.toml` entry written to `:root`; `dx.light` when unset.
+    #[serde(default)]
+    pub root_theme: Option<String>,
+    /// Seed color (`#rrggbb`) of a generated palette that replaces the `themes.toml` root theme.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Image whose best-scoring color becomes `source` when `source` is not set.
+    #[serde(default)]
+    pub source_image: Option<String>,
+    /// Scheme variant of the generated palette; `tonal_spot` when unset.
+    #[serde(default)]
+    pub variant: Option<Variant>,
+    /// Contrast of the generated palette, from -1.0 (reduced) to 1.0 (high); 0.0 is the default.
+    #[serde(default)]
+    pub contrast_level: Option<f64>,
+    /// Role colors that override the palettes derived from `source`.
+    #[serde(default)]
+    pub primary: Option<String>,
+    #[serde(default)]
+    pub secondary: Option<String>,
+    #[serde(default)]
+    pub tertiary: Option<String>,
+    /// Extra semantic colors such as `success = { value = "#16a34a", blend = true }`.
+    #[serde(default)]
+    pub custom_colors: BTreeMap<String, CustomColorConfig>,
+}
+
+/// One `[theme.custom_colors]` entry.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct CustomColorConfig {
+    pub value: String,
+    /// Shift the hue towards the theme source so the color sits with the palette.
+    #[serde(default = "CustomColorConfig::default_blend")]
+    pub blend: bool,
+}
+
+impl CustomColorConfig {
+    fn default_blend() -> bool {
+        true
+    }
+}
+
+impl ThemeConfig {
+    /// Selector that switches a subtree to dark; `None` when dark mode only follows the media query.
+    pub fn dark_selector(&self) -> Option<&str> {
+        if self.dark_mode == DarkMode::Media {
+            return None;
+        }
+        Some(match (&self.dark_selector, self.dark_mode) {
+            (Some(custom), _) if !custom.trim().is_empty() => custom.trim(),
+            (_, DarkMode::DataAttribute) => "[data-mode=\"dark\"]",
+            _ => ".dark",
+        })
+    }
+
+    pub fn root_theme(&self) -> &str {
+        self.root_theme
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .unwrap_or("dx.light")
+    }
+
+    /// Dark counterpart of the root theme: `acme.light` pairs with `acme.dark`.
+    pub fn dark_theme(&self) -> String {
+        let root = self.root_theme();
+        match root.strip_suffix("light") {
+            Some(family) => format!("{}dark", family),
+            None => format!("{}.dark", root),
+        }
+    }
+
+    /// Checks the color and contrast options so mistakes surface when the config is loaded.
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        for (key, value) in [
+            ("source", &self.source),
+            ("primary", &self.primary),
+            ("secondary", &self.secondary),
+            ("tertiary", &self.tertiary),
+        ] {
+            if let Some(value) = value {
+                parse_theme_color(key, value)?;
+            }
+        }
+        for (name, color) in &self.custom_colors {
+            let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !valid_name {
+                return Err(format!(
+                    "[theme.custom_colors] {:?} must start with a letter and use only letters, digits and '-'",
+                    name
+                )
+                .into());
+            }
+            if name.starts_with("on-") || name.ends_with("-container") {
+                return Err(format!(
+                    "[theme.custom_colors] {:?}: `on-` and `-container` names are generated for every custom color",
+                    name
+                )
+                .into());
+            }
+            if let Some(token) = [
+                name.clone(),
+                format!("on-{}", name),
+                format!("{}-container", name),
+                format!("on-{}-container", name),
+            ]
+            .into_iter()
+            .find(|token| crate::core::is_theme_token(token))
+            {
+                return Err(format!(
+                    "[theme.custom_colors] {:?} would redefine the theme's --{}",
+                    name, token
+                )
+                .into());
+            }
+            parse_theme_color(&format!("custom_colors.{}", name), &color.value)?;
+        }
+        if let Some(level) = self
+            .contrast_level
+            .filter(|level| !(-1.0..=1.0).contains(level))
+        {
+            return Err(format!(
+                "[theme] contrast_level must be between -1.0 and 1.0, got {}",
+                level
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Fills `source` from `source_image` when only the image is given.
+    pub fn resolve_source_image(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = self
+            .source_image
+            .as_deref()
+            .filter(|_| self.source.is_none())
+        else {
+            return Ok(());
+        };
+        #[cfg(feature = "image")]
+        {
+            let colors = crate::core::color::image::ImageReader::source_colors(path, 1)
+                .map_err(|e| format!("[theme] source_image: {}", e))?;
+            let seed = colors
+                .first()
+                .ok_or_else(|| format!("[theme] source_image: no colors found in {}", path))?;
+            self.source = Some(seed.to_hex_with_pound());
+            Ok(())
+        }
+        #[cfg(not(feature = "image"))]
+        {
+            Err(format!(
+                "[theme] source_image ({}) needs dx-style built with the `image` feature",
+                path
+            )
+            .into())
+        }
+    }
+
+    /// True when dark mode also follows `prefers-color-scheme`.
+    pub fn follows_media(&self) -> bool {
+        matches!(self.dark_mode, DarkMode::Media | DarkMode::Both)
+    }
+}
+
+/// Parses a `[theme]` hex color such as `#0ea5e9`.
+pub fn parse_theme_color(key: &str, value: &str) -> Result<Argb, String> {
+    value.trim().parse::<Argb>().map_err(|_| {
+        format!(
+            "[theme] {} must be a hex color like \"#0ea5e9\", got {:?}",
+            key, value
+        )
+    })
+}
+
+impl DiagnosticsConfig {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    fn default_min_contrast_ratio() -> f64 {
+        4.5
+    }
+
+    fn default_min_apca_lc() -> f64 {
+        60.0
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl Default for DiagnosticsConfig {
+    fn default() -> Self {
+        DiagnosticsConfig {
+            enabled: DiagnosticsConfig::default_enabled(),
+            strict: false,
+            contrast: false,
+            min_contrast_ratio: DiagnosticsConfig::default_min_contrast_ratio(),
+            min_apca_lc: DiagnosticsConfig::default_min_apca_lc(),
+        }
+    }
+}
+
+impl ParserConfig {
+    fn default_incremental() -> bool {
+        true
+    }
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        ParserConfig {
+            incremental: ParserConfig::default_incremental(),
+            debug: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct FormatConfig {
     #[serde(default = "FormatConfig::default_delay")]
     pub delay_ms: u64,
@@ -37,6 +393,17 @@ pub struct FormatConfig {
     pub debounce_ms: u64,
 }
 
+impl Default for FormatConfig {
+    fn default() -> Self {
+        FormatConfig {
+            delay_ms: FormatConfig::default_delay(),
+            interval_ms: FormatConfig::default_interval(),
+            force_write: false,
+            debounce_ms: FormatConfig::default_debounce(),
+        }
+    }
+}
+
 impl FormatConfig {
     fn default_delay() -> u64 {
         10_000
@@ -50,9 +417,11 @@ impl FormatConfig {
 }
 
 impl Config {
-    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        let content = fs::read_to_string(".dx/config.toml")?;
+    pub fn load_from(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read config {}: {}", path, e))?;
         let cfg: Config = toml::from_str(&content)?;
+        cfg.theme.validate()?;
         Ok(cfg)
     }
 }
@@ -69,18 +438,76 @@ impl Default for Config {
             },
             watch: Some(WatchConfig {
                 debounce_ms: Some(250),
+                ..WatchConfig::default()
             }),
-            format: Some(FormatConfig {
-                delay_ms: FormatConfig::default_delay(),
-                interval_ms: FormatConfig::default_interval(),
-                force_write: false,
-                debounce_ms: FormatConfig::default_debounce(),
-            }),
+            format: Some(FormatConfig::default()),
+            content: Vec::new(),
+            grouping: GroupingConfig::default(),
+            output: OutputConfig::default(),
+            parser: ParserConfig::default(),
+            diagnostics: DiagnosticsConfig::default(),
+            theme: ThemeConfig::default(),
         }
     }
 }
 
+/// `DX_*` variables that still override the config file, e.g. for one-off debugging runs.
+fn env_flag(name: &str) -> Option<bool> {
+    std::env::var(name).ok().map(|v| v == "1" || v == "true")
+}
+
+fn env_parse<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok().and_then(|v| v.parse().ok())
+}
+
 impl Config {
+    pub fn apply_env_overrides(&mut self) {
+        if let Some(v) = env_parse("DX_GROUP_RENAME_SIMILARITY") {
+            self.grouping.rename_similarity = v;
+        }
+        if let Some(v) = env_flag("DX_GROUP_AGGRESSIVE_REWRITE") {
+            self.grouping.aggressive_rewrite = v;
+        }
+        if let Some(v) = env_parse("DX_GROUP_REWRITE_UTILITY_OVERLAP") {
+            self.grouping.utility_overlap = v;
+        }
+        if let Some(v) = env_flag("DX_GROUP_REWRITE_PLAIN_ALIAS") {
+            self.grouping.rewrite_plain_alias = v;
+        }
+        if let Some(v) = env_parse("DX_MMAP_THRESHOLD") {
+            self.output.mmap_threshold = v;
+        }
+        if let Some(v) = env_parse("DX_FLUSH_INTERVAL_MS") {
+            self.output.flush_interval_ms = v;
+        }
+        if let Some(v) = env_flag("DX_FORCE_FULL") {
+            self.output.force_full = v;
+        }
+        if let Some(v) = env_parse("DX_DEBOUNCE_MS") {
+            self.watch
+                .get_or_insert_with(WatchConfig::default)
+                .debounce_ms = Some(v);
+        }
+        if let Some(v) = env_parse("DX_WATCH_POLL_MS") {
+            self.watch.get_or_insert_with(WatchConfig::default).poll_ms = Some(v);
+        }
+        if let Some(v) = env_flag("DX_WATCH_RAW") {
+            self.watch.get_or_insert_with(WatchConfig::default).raw = v;
+        }
+        if let Some(v) = env_flag("DX_DISABLE_INCREMENTAL") {
+            self.parser.incremental = !v;
+        }
+        if let Some(v) = env_flag("DX_DEBUG") {
+            self.parser.debug = v;
+        }
+    }
+
+    pub fn watch_poll_ms(&self) -> Option<u64> {
+        self.watch.as_ref().and_then(|w| w.poll_ms)
+    }
+    pub fn watch_raw(&self) -> bool {
+        self.watch.as_ref().map(|w| w.raw).unwrap_or(false)
+    }
     pub fn resolved_style_dir(&self) -> &str {
         self.paths.style_dir.as_deref().unwrap_or(".dx/style")
     }
@@ -109,3 +536,105 @@ impl Config {
             .unwrap_or(FormatConfig::default_debounce())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn behaviour_sections_default_when_missing() {
+        let minimal = r#"
+            [paths]
+            html_dir = "site"
+            index_file = "site/index.html"
+            css_file = "site/style.css"
+        "#;
+        let cfg: Config = toml::from_str(minimal).unwrap();
+        assert_eq!(cfg.grouping.rename_similarity, 0.6);
+        assert!(cfg.parser.incremental);
+        assert_eq!(cfg.output.mmap_threshold, 64 * 1024);
+
+        let tuned = format!(
+            "{}\n[grouping]\naggressive_rewrite = true\n[parser]\nincremental = false\n[watch]\npoll_ms = 100\n",
+            minimal
+        );
+        let cfg: Config = toml::from_str(&tuned).unwrap();
+        assert!(cfg.grouping.aggressive_rewrite);
+        assert_eq!(cfg.grouping.utility_overlap, 0.5);
+        assert!(!cfg.parser.incremental);
+        assert_eq!(cfg.watch_poll_ms(), Some(100));
+    }
+
+    #[test]
+    fn dark_mode_selects_the_dark_selector() {
+        let theme = |toml: &str| -> ThemeConfig { toml::from_str(toml).unwrap() };
+        assert_eq!(theme("").dark_selector(), Some(".dark"));
+        assert_eq!(
+            theme("dark_mode = \"data-attribute\"").dark_selector(),
+            Some("[data-mode=\"dark\"]")
+        );
+        assert_eq!(theme("dark_mode = \"media\"").dark_selector(), None);
+        let both = theme("dark_mode = \"both\"\ndark_selector = '[data-mode=\"night\"]'");
+        assert_eq!(both.dark_selector(), Some("[data-mode=\"night\"]"));
+        assert!(both.follows_media());
+    }
+
+    #[test]
+    fn theme_colors_are_validated() {
+        let theme = |toml: &str| -> ThemeConfig { toml::from_str(toml).unwrap() };
+        assert!(
+            theme("source = \"#0ea5e9\"\nvariant = \"fruit_salad\"\ncontrast_level = 0.5")
+                .validate()
+                .is_ok()
+        );
+        let err = theme("primary = \"blue\"").validate().unwrap_err();
+        assert!(err.to_string().contains("[theme] primary"));
+        assert!(theme("contrast_level = 2.0").validate().is_err());
+        assert!(toml::from_str::<ThemeConfig>("variant = \"sparkly\"").is_err());
+    }
+
+    #[test]
+    fn custom_colors_default_to_blending_and_are_validated() {
+        let theme = |toml: &str| -> ThemeConfig { toml::from_str(toml).unwrap() };
+        let ok = theme(
+            "[custom_colors]\nsuccess = { value = \"#16a34a\" }\nwarning = { value = \"#f59e0b\", blend = false }",
+        );
+        assert!(ok.validate().is_ok());
+        assert!(ok.custom_colors["success"].blend);
+        assert!(!ok.custom_colors["warning"].blend);
+        let err = theme("[custom_colors]\ninfo = { value = \"sky\" }")
+            .validate()
+            .unwrap_err();
+        assert!(err.to_string().contains("[theme] custom_colors.info"));
+        assert!(
+            theme("[custom_colors]\n\"on success\" = { value = \"#16a34a\" }")
+                .validate()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn custom_colors_must_not_redefine_theme_tokens() {
+        let validate = |name: &str| {
+            let toml = format!("[custom_colors]\n{} = {{ value = \"#16a34a\" }}", name);
+            toml::from_str::<ThemeConfig>(&toml).unwrap().validate()
+        };
+        for name in [
+            "primary",
+            "background",
+            "chart-1",
+            "neutral",
+            "tertiary-40",
+            "on-primary",
+            "brand-container",
+            "sidebar",
+        ] {
+            let err = validate(name).unwrap_err();
+            assert!(err.to_string().contains(name), "{}: {}", name, err);
+        }
+        let err = validate("sidebar-accent").unwrap_err();
+        assert!(err.to_string().contains("--sidebar-accent"));
+        assert!(validate("brand").is_ok());
+        assert!(validate("chart-6").is_ok());
+    }
+}
@@ -83,6 +83,7 @@ pub fn decode_animation_if_pending(
     selector: &str,
     pending: &mut Option<PendingAnimation>,
     out: &mut String,
+    important: bool,
 ) {
     if let Some(pa) = pending.take() {
         if !pa.has_main {
@@ -155,6 +156,7 @@ pub fn decode_animation_if_pending(
             out.push_str(&build_block(
                 base_selector,
                 &format!("animation: {}", value),
+                important,
             ));
         }
     }
@@ -1,4 +1,4 @@
-use crate::core::color::color::{lstar_from_y, y_from_lstar};
+use crate::core::color::color::{Argb, lstar_from_y, y_from_lstar};
 #[cfg(all(not(feature = "std"), feature = "libm"))]
 #[allow(unused_imports)]
 use crate::core::color::utils::no_std::FloatExt;
@@ -14,6 +14,58 @@ pub fn ratio_of_tones(tone_a: f64, tone_b: f64) -> f64 {
     ratio_of_ys(y_from_lstar(tone_a), y_from_lstar(tone_b))
 }
 
+/// Returns the WCAG 2 contrast ratio of two colors, which ranges from 1 to 21.
+pub fn ratio_of_colors(a: Argb, b: Argb) -> f64 {
+    ratio_of_tones(a.as_lstar(), b.as_lstar())
+}
+
+/// Returns the APCA lightness contrast (Lc) of `text` on `background`, following APCA-W3 0.0.98G.
+///
+/// Dark text on a light background gives a positive Lc (up to about 106), light text on a dark
+/// background a negative one (down to about -108). Compare thresholds against the absolute value.
+pub fn apca_contrast(text: Argb, background: Argb) -> f64 {
+    const BLACK_THRESHOLD: f64 = 0.022;
+    const BLACK_CLAMP: f64 = 1.414;
+    const DELTA_Y_MIN: f64 = 0.0005;
+    const SCALE: f64 = 1.14;
+    const LOW_CLIP: f64 = 0.1;
+    const LOW_OFFSET: f64 = 0.027;
+
+    let screen_y = |color: Argb| {
+        let channel = |c: u8| (f64::from(c) / 255.0).powf(2.4);
+        let y = 0.212_672_9 * channel(color.red)
+            + 0.715_152_2 * channel(color.green)
+            + 0.072_175 * channel(color.blue);
+        if y < BLACK_THRESHOLD {
+            y + (BLACK_THRESHOLD - y).powf(BLACK_CLAMP)
+        } else {
+            y
+        }
+    };
+    let text_y = screen_y(text);
+    let background_y = screen_y(background);
+    if (background_y - text_y).abs() < DELTA_Y_MIN {
+        return 0.0;
+    }
+
+    let lc = if background_y > text_y {
+        let sapc = (background_y.powf(0.56) - text_y.powf(0.57)) * SCALE;
+        if sapc < LOW_CLIP {
+            0.0
+        } else {
+            sapc - LOW_OFFSET
+        }
+    } else {
+        let sapc = (background_y.powf(0.65) - text_y.powf(0.62)) * SCALE;
+        if sapc > -LOW_CLIP {
+            0.0
+        } else {
+            sapc + LOW_OFFSET
+        }
+    };
+    lc * 100.0
+}
+
 fn ratio_of_ys(y1: f64, y2: f64) -> f64 {
     let lighter = if y1 > y2 { y1 } else { y2 };
     let darker = if (lighter - y2).abs() < f64::EPSILON {
@@ -122,15 +174,36 @@ pub fn darker_unsafe(tone: f64, ratio: f64) -> f64 {
 mod tests {
     use float_cmp::assert_approx_eq;
 
+    use crate::core::color::color::Argb;
     use crate::core::color::contrast::ratio_of_tones;
 
-    use super::{darker, darker_unsafe, lighter, lighter_unsafe};
+    use super::{apca_contrast, darker, darker_unsafe, lighter, lighter_unsafe, ratio_of_colors};
 
     #[test]
     fn test_ratio_of_tones_out_of_bounds_input() {
         assert_approx_eq!(f64, 21.0, ratio_of_tones(-10.0, 110.0), epsilon = 0.001);
     }
 
+    #[test]
+    fn test_ratio_of_colors_matches_wcag() {
+        let white = Argb::from_u32(0xFFFFFFFF);
+        let black = Argb::from_u32(0xFF000000);
+        assert_approx_eq!(f64, 21.0, ratio_of_colors(white, black), epsilon = 0.01);
+        let gray = Argb::from_u32(0xFF777777);
+        assert_approx_eq!(f64, 4.48, ratio_of_colors(gray, white), epsilon = 0.01);
+    }
+
+    #[test]
+    fn test_apca_contrast_reference_values() {
+        let white = Argb::from_u32(0xFFFFFFFF);
+        let black = Argb::from_u32(0xFF000000);
+        assert_approx_eq!(f64, 106.04, apca_contrast(black, white), epsilon = 0.01);
+        assert_approx_eq!(f64, -107.88, apca_contrast(white, black), epsilon = 0.01);
+        let gray = Argb::from_u32(0xFF888888);
+        assert_approx_eq!(f64, 63.06, apca_contrast(gray, white), epsilon = 0.01);
+        assert_approx_eq!(f64, 0.0, apca_contrast(gray, gray), epsilon = 0.001);
+    }
+
     #[test]
     fn test_lighter_impossible_ratio_errors() {
         assert_approx_eq!(f64, -1.0, lighter(90.0, 10.0), epsilon = 0.001);
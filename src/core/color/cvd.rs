@@ -0,0 +1,181 @@
+#[cfg(all(not(feature = "std"), feature = "libm"))]
+#[allow(unused_imports)]
+use crate::core::color::utils::no_std::FloatExt;
+use crate::core::color::{
+    color::{Argb, delinearized, linearized},
+    hct::Cam16,
+};
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::{string::String, vec::Vec};
+
+/// Color vision deficiency simulated by [`simulate`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Deficiency {
+    /// No L cones (red-blind).
+    Protanopia,
+    /// No M cones (green-blind).
+    Deuteranopia,
+    /// No S cones (blue-blind).
+    Tritanopia,
+    /// No cone vision; only lightness is seen.
+    Achromatopsia,
+}
+
+impl Deficiency {
+    pub const ALL: [Self; 4] = [
+        Self::Protanopia,
+        Self::Deuteranopia,
+        Self::Tritanopia,
+        Self::Achromatopsia,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Protanopia => "protanopia",
+            Self::Deuteranopia => "deuteranopia",
+            Self::Tritanopia => "tritanopia",
+            Self::Achromatopsia => "achromatopsia",
+        }
+    }
+
+    /// Linear-sRGB transform of the deficiency.
+    ///
+    /// The dichromacies use the full-severity matrices of Machado, Oliveira and Fernandes (2009);
+    /// achromatopsia maps every channel to the Rec. 709 luminance.
+    pub const fn matrix(self) -> [[f64; 3]; 3] {
+        match self {
+            Self::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Self::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Self::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+            Self::Achromatopsia => [[0.2126, 0.7152, 0.0722]; 3],
+        }
+    }
+}
+
+/// Returns how `color` appears with `deficiency`. Alpha is kept.
+pub fn simulate(color: Argb, deficiency: Deficiency) -> Argb {
+    let rgb = [
+        linearized(color.red),
+        linearized(color.green),
+        linearized(color.blue),
+    ];
+    let [red, green, blue] = deficiency.matrix().map(|row| {
+        let value = row[2].mul_add(rgb[2], row[0].mul_add(rgb[0], row[1] * rgb[1]));
+        delinearized(value.clamp(0.0, 100.0))
+    });
+
+    Argb::new(color.alpha, red, green, blue)
+}
+
+/// Returns the CAM16-UCS color difference (ΔE') between two colors.
+pub fn ucs_distance(a: Argb, b: Argb) -> f64 {
+    Cam16::from(a).distance(&Cam16::from(b))
+}
+
+/// A pair of tokens that are too close with one deficiency.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollapsedPair {
+    pub first: String,
+    pub second: String,
+    pub deficiency: Deficiency,
+    /// ΔE' of the simulated colors.
+    pub distance: f64,
+    /// ΔE' with typical color vision, for comparison.
+    pub normal_distance: f64,
+}
+
+/// Simulates every deficiency on `tokens` and returns the `pairs` whose simulated colors are
+/// closer than `min_distance`. Pairs naming a missing token are skipped.
+pub fn collapsed_pairs(
+    tokens: &[(String, Argb)],
+    pairs: &[(String, String)],
+    min_distance: f64,
+) -> Vec<CollapsedPair> {
+    let color = |name: &str| {
+        tokens
+            .iter()
+            .find(|(token, _)| token == name)
+            .map(|(_, color)| *color)
+    };
+    let mut out = Vec::new();
+    for (first, second) in pairs {
+        let (Some(a), Some(b)) = (color(first), color(second)) else {
+            continue;
+        };
+        let normal_distance = ucs_distance(a, b);
+        for deficiency in Deficiency::ALL {
+            let distance = ucs_distance(simulate(a, deficiency), simulate(b, deficiency));
+            if distance < min_distance {
+                out.push(CollapsedPair {
+                    first: first.clone(),
+                    second: second.clone(),
+                    deficiency,
+                    distance,
+                    normal_distance,
+                });
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use float_cmp::assert_approx_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_simulation_keeps_neutrals_and_drops_hue() {
+        let gray = Argb::from_u32(0xFF808080);
+        for deficiency in Deficiency::ALL {
+            let simulated = simulate(gray, deficiency);
+            assert!(ucs_distance(gray, simulated) < 1.0, "{:?}", deficiency);
+        }
+        let red = Argb::from_u32(0xFFFF0000);
+        let simulated = simulate(red, Deficiency::Achromatopsia);
+        assert_eq!(simulated.red, simulated.green);
+        assert_eq!(simulated.green, simulated.blue);
+    }
+
+    #[test]
+    fn test_red_and_green_collapse_for_deuteranopia_not_tritanopia() {
+        let tokens = [
+            ("error".to_string(), Argb::from_u32(0xFFD32F2F)),
+            ("success".to_string(), Argb::from_u32(0xFF689F38)),
+        ];
+        let pairs = [("error".to_string(), "success".to_string())];
+        assert!(ucs_distance(tokens[0].1, tokens[1].1) > 15.0);
+        let collapsed = collapsed_pairs(&tokens, &pairs, 8.0);
+        assert!(
+            collapsed
+                .iter()
+                .any(|p| p.deficiency == Deficiency::Deuteranopia)
+        );
+        assert!(
+            collapsed
+                .iter()
+                .all(|p| p.deficiency != Deficiency::Tritanopia)
+        );
+        assert_approx_eq!(
+            f64,
+            collapsed[0].normal_distance,
+            ucs_distance(tokens[0].1, tokens[1].1)
+        );
+    }
+}
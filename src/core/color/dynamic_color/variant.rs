@@ -3,7 +3,10 @@
 /// colors corresponding to the theme.
 ///
 /// [`SchemeTonalSpot`]: crate::core::color::scheme::variant::SchemeTonalSpot
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
 pub enum Variant {
     Monochrome,
     Neutral,
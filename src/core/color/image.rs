@@ -4,11 +4,19 @@ use crate::core::color::{
 pub use images::imageops::FilterType;
 use images::{ImageReader as Reader, RgbaImage, imageops::resize};
 use std::{
-    io::{Cursor, Result},
+    io::{self, Cursor, Result},
     path::Path,
     vec::Vec,
 };
 
+/// Longest side images are scaled down to before quantizing; the ranking barely changes and
+/// large logos and photos stay fast.
+const MAX_QUANTIZE_SIDE: u32 = 128;
+
+fn decode_error(error: images::ImageError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
 pub struct Image {
     image: RgbaImage,
 }
@@ -18,6 +26,20 @@ impl Image {
         Self { image }
     }
 
+    /// Scales the image down so neither side exceeds `max_side`, keeping its aspect ratio.
+    pub fn shrink_to(&mut self, max_side: u32) -> &mut Self {
+        let (width, height) = self.image.dimensions();
+        let longest = width.max(height);
+        if longest > max_side {
+            let scale = f64::from(max_side) / f64::from(longest);
+            let width = ((f64::from(width) * scale).round() as u32).max(1);
+            let height = ((f64::from(height) * scale).round() as u32).max(1);
+            self.resize(width, height, FilterType::Triangle);
+        }
+
+        self
+    }
+
     pub fn resize(&mut self, width: u32, height: u32, filter_type: FilterType) -> &mut Self {
         self.image = resize(&self.image, width, height, filter_type);
 
@@ -52,7 +74,7 @@ impl ImageReader {
         let data = Reader::new(Cursor::new(data))
             .with_guessed_format()?
             .decode()
-            .expect("failed to decode image")
+            .map_err(decode_error)?
             .into_rgba8();
 
         Ok(Image::new(data))
@@ -62,10 +84,16 @@ impl ImageReader {
     where
         P: AsRef<Path>,
     {
-        let data = Reader::open(path)?
-            .with_guessed_format()?
+        let path = path.as_ref();
+        let with_path = |error: io::Error| {
+            io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+        };
+        let data = Reader::open(path)
+            .map_err(with_path)?
+            .with_guessed_format()
+            .map_err(with_path)?
             .decode()
-            .expect("failed to decode image")
+            .map_err(|e| with_path(decode_error(e)))?
             .into_rgba8();
 
         Ok(Image::new(data))
@@ -86,4 +114,59 @@ impl ImageReader {
 
         ranked[0]
     }
+    /// Get up to `count` source color candidates from an image, best first.
+    pub fn extract_colors<I>(image: &I, count: usize) -> Vec<Argb>
+    where
+        I: AsPixels,
+    {
+        let pixels = image.as_pixels();
+        let result = QuantizerCelebi::quantize(&pixels, 128);
+        let desired = i32::try_from(count.max(1)).unwrap_or(i32::MAX);
+
+        Score::score(&result.color_to_count, Some(desired), None, None)
+    }
+
+    /// Opens an image file and returns up to `count` scored source colors, best first.
+    pub fn source_colors<P>(path: P, count: usize) -> Result<Vec<Argb>>
+    where
+        P: AsRef<Path>,
+    {
+        let mut image = Self::open(path)?;
+        image.shrink_to(MAX_QUANTIZE_SIDE);
+
+        Ok(Self::extract_colors(&image, count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(width: u32, height: u32, pixel: [u8; 4]) -> Vec<u8> {
+        let image = RgbaImage::from_pixel(width, height, images::Rgba(pixel));
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), images::ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn undecodable_images_are_errors() {
+        let mut truncated = png(4, 4, [14, 165, 233, 255]);
+        truncated.truncate(40);
+        assert!(ImageReader::read(&truncated).is_err());
+        assert!(ImageReader::read(b"not an image").is_err());
+        let missing = ImageReader::open("missing/logo.png").err().unwrap();
+        assert!(missing.to_string().contains("missing/logo.png"));
+    }
+
+    #[test]
+    fn extracts_the_dominant_color_of_a_shrunk_image() {
+        let mut image = ImageReader::read(png(300, 150, [14, 165, 233, 255])).unwrap();
+        image.shrink_to(MAX_QUANTIZE_SIDE);
+        assert_eq!(image.image.dimensions(), (128, 64));
+        let colors = ImageReader::extract_colors(&image, 3);
+        assert_eq!(colors.first(), Some(&Argb::new(255, 14, 165, 233)));
+    }
 }
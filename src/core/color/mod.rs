@@ -1,7 +1,7 @@
 #![allow(dead_code)] // Color module API surface is broader than current in-crate usage.
 
 use crate::core::color::color::{Argb, Oklch};
-use crate::core::engine::StyleEngine;
+use crate::core::engine::{ColorUtility, StyleEngine};
 
 #[cfg(all(feature = "image", not(feature = "std")))]
 compile_error!("\"image\" feature requires \"std\" feature");
@@ -29,6 +29,7 @@ pub(crate) type IndexMap<K, V> =
 pub mod blend;
 pub mod color;
 pub mod contrast;
+pub mod cvd;
 pub mod dislike;
 pub mod dynamic_color;
 pub mod error;
@@ -46,19 +47,126 @@ pub mod utils;
 pub use error::Error;
 
 pub fn generate_color_css(engine: &StyleEngine, class_name: &str) -> Option<String> {
-    if let Some(name) = class_name.strip_prefix("bg-") {
-        if derive_color_value(engine, name).is_some() {
-            return Some(format!("background-color: var(--color-{})", name));
-        }
+    let found = match_color_utility(engine, class_name)?;
+    let reference = color_reference(engine, found.name)?;
+    let value = apply_alpha(&reference, found.alpha.as_deref());
+    let decls = found
+        .utility
+        .properties
+        .iter()
+        .map(|property| format!("{}: {}", property, value))
+        .collect::<Vec<_>>()
+        .join("; ");
+    match &found.utility.selector {
+        Some(selector) => Some(format!("CHILD|{}|{}", selector, decls)),
+        None => Some(decls),
     }
-    if let Some(name) = class_name.strip_prefix("text-") {
-        if derive_color_value(engine, name).is_some() {
-            return Some(format!("color: var(--color-{})", name));
-        }
+}
+
+/// A class split against the configured color utilities, e.g. `border-x-primary/50`.
+pub struct ColorMatch<'a> {
+    pub utility: &'a ColorUtility,
+    pub name: &'a str,
+    pub alpha: Option<String>,
+}
+
+/// Finds the longest color utility prefix of `class_name` and splits off the alpha modifier.
+/// The color name itself is not validated; see [`color_reference`].
+pub fn match_color_utility<'a>(
+    engine: &'a StyleEngine,
+    class_name: &'a str,
+) -> Option<ColorMatch<'a>> {
+    engine.color_utilities.iter().find_map(|utility| {
+        let rest = class_name
+            .strip_prefix(utility.prefix.as_str())?
+            .strip_prefix('-')?;
+        let (name, alpha) = split_alpha_modifier(rest)?;
+        (!name.is_empty()).then_some(ColorMatch {
+            utility,
+            name,
+            alpha,
+        })
+    })
+}
+
+/// The `var()` a color utility points at: `--color-<name>` for colors.toml entries, keywords and
+/// hex codes, `--<name>` for tokens defined in themes.toml.
+pub fn color_reference(engine: &StyleEngine, name: &str) -> Option<String> {
+    if derive_color_value(engine, name).is_some() {
+        return Some(format!("var(--color-{})", name));
+    }
+    if engine.has_theme_token(name) {
+        return Some(format!("var(--{})", name));
+    }
+    if let Some((palette, tone)) = parse_tonal_step(name).filter(|_| engine.has_tonal_palettes()) {
+        return Some(format!("var(--{}-{})", palette, tone));
     }
     None
 }
 
+/// Theme palettes a tonal step can name.
+pub const TONAL_PALETTES: &[&str] = &[
+    "primary",
+    "secondary",
+    "tertiary",
+    "neutral",
+    "neutral-variant",
+    "error",
+];
+
+/// Splits `primary-40` or `tertiary-[73]` into the palette and its HCT tone (0 to 100).
+pub fn parse_tonal_step(name: &str) -> Option<(&str, i32)> {
+    let (palette, step) = name.rsplit_once('-')?;
+    let step = step
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .unwrap_or(step);
+    if step.is_empty() || !step.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let tone: i32 = step.parse().ok().filter(|tone| *tone <= 100)?;
+    TONAL_PALETTES.contains(&palette).then_some((palette, tone))
+}
+
+/// Splits `primary/50` or `red/[.35]` into the color name and an alpha percentage.
+/// Returns `None` when a modifier is present but is not a valid alpha.
+pub fn split_alpha_modifier(value: &str) -> Option<(&str, Option<String>)> {
+    match value.rsplit_once('/') {
+        Some((name, modifier)) => Some((name, Some(parse_alpha(modifier)?))),
+        None => Some((value, None)),
+    }
+}
+
+/// Parses the part after `/`: `50` (percent) or `[.35]`, `[35%]`, `[var(--x)]`.
+pub fn parse_alpha(modifier: &str) -> Option<String> {
+    if let Some(inner) = modifier.strip_prefix('[').and_then(|m| m.strip_suffix(']')) {
+        if inner.starts_with("var(") {
+            return Some(inner.to_string());
+        }
+        if let Some(pct) = inner.strip_suffix('%') {
+            let n = pct.parse::<f64>().ok()?;
+            return (0.0..=100.0).contains(&n).then(|| format_percent(n));
+        }
+        let n = inner.parse::<f64>().ok()?;
+        return (0.0..=1.0).contains(&n).then(|| format_percent(n * 100.0));
+    }
+    let n = modifier.parse::<f64>().ok()?;
+    (0.0..=100.0).contains(&n).then(|| format_percent(n))
+}
+
+fn format_percent(value: f64) -> String {
+    let rounded = (value * 1000.0).round() / 1000.0;
+    format!("{}%", rounded)
+}
+
+/// Mixes `color` with transparent in OKLCH when an alpha modifier is present.
+pub fn apply_alpha(color: &str, alpha: Option<&str>) -> String {
+    match alpha {
+        Some("100%") | None => color.to_string(),
+        Some(alpha) => format!("color-mix(in oklch, {} {}, transparent)", color, alpha),
+    }
+}
+
 // --- Dynamic color token support -----------------------------------------------------------
 // Any class "bg-<token>" or "text-<token>" will now produce a CSS variable --color-<token>.
 // 1. If <token> exists in colors.toml it uses that value.
@@ -220,6 +328,11 @@ const CSS_COLOR_KEYWORDS: &[&str] = &[
     "currentcolor",
 ];
 
+/// Named CSS colors, for did-you-mean suggestions.
+pub fn css_color_keywords() -> &'static [&'static str] {
+    CSS_COLOR_KEYWORDS
+}
+
 pub fn derive_color_value(engine: &StyleEngine, name: &str) -> Option<String> {
     if let Some(v) = engine.colors.get(name) {
         return Some(v.clone());
@@ -513,3 +626,101 @@ pub(crate) fn format_argb_as_oklch(color: Argb) -> String {
 pub(crate) fn normalize_color_to_oklch(value: &str) -> Option<String> {
     parse_color_to_argb(value).map(format_argb_as_oklch)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::ThemeDefinition;
+
+    #[test]
+    fn alpha_modifier_parses_percent_and_bracket_forms() {
+        assert_eq!(split_alpha_modifier("primary"), Some(("primary", None)));
+        assert_eq!(
+            split_alpha_modifier("primary/50"),
+            Some(("primary", Some("50%".to_string())))
+        );
+        assert_eq!(
+            split_alpha_modifier("red/[.35]"),
+            Some(("red", Some("35%".to_string())))
+        );
+        assert_eq!(split_alpha_modifier("red/[1.5]"), None);
+        assert_eq!(split_alpha_modifier("red/150"), None);
+    }
+
+    #[test]
+    fn color_utilities_mix_alpha_for_colors_and_theme_tokens() {
+        let mut engine = StyleEngine::empty();
+        engine
+            .colors
+            .insert("brand".to_string(), "#ff0000".to_string());
+        engine.themes.push(ThemeDefinition {
+            name: "dx.light".to_string(),
+            tokens: vec![("primary".to_string(), "oklch(0.5 0.1 250)".to_string())],
+        });
+        assert_eq!(
+            generate_color_css(&engine, "bg-primary/50").as_deref(),
+            Some("background-color: color-mix(in oklch, var(--primary) 50%, transparent)")
+        );
+        assert_eq!(
+            generate_color_css(&engine, "text-red/[.35]").as_deref(),
+            Some("color: color-mix(in oklch, var(--color-red) 35%, transparent)")
+        );
+        assert_eq!(
+            generate_color_css(&engine, "bg-brand").as_deref(),
+            Some("background-color: var(--color-brand)")
+        );
+        assert!(generate_color_css(&engine, "bg-brand/abc").is_none());
+        assert_eq!(
+            generate_color_css(&engine, "shadow-primary").as_deref(),
+            Some("--shadow-color: var(--primary)")
+        );
+    }
+
+    #[test]
+    fn tonal_steps_reference_one_variable_per_tone() {
+        let engine = StyleEngine::empty();
+        assert_eq!(parse_tonal_step("primary-40"), Some(("primary", 40)));
+        assert_eq!(
+            parse_tonal_step("neutral-variant-[95]"),
+            Some(("neutral-variant", 95))
+        );
+        assert_eq!(parse_tonal_step("primary-101"), None);
+        assert_eq!(parse_tonal_step("primary-[+5]"), None);
+        assert_eq!(parse_tonal_step("brand-40"), None);
+        assert_eq!(
+            generate_color_css(&engine, "border-tertiary-[73]").as_deref(),
+            Some("border-color: var(--tertiary-73)")
+        );
+        assert_eq!(
+            generate_color_css(&engine, "text-neutral-90/50").as_deref(),
+            Some("color: color-mix(in oklch, var(--neutral-90) 50%, transparent)")
+        );
+    }
+
+    #[test]
+    fn every_color_prefix_resolves_to_its_properties() {
+        let engine = StyleEngine::empty();
+        assert_eq!(
+            generate_color_css(&engine, "border-x-red").as_deref(),
+            Some("border-left-color: var(--color-red); border-right-color: var(--color-red)")
+        );
+        assert_eq!(
+            generate_color_css(&engine, "fill-blue/50").as_deref(),
+            Some("fill: color-mix(in oklch, var(--color-blue) 50%, transparent)")
+        );
+        assert_eq!(
+            generate_color_css(&engine, "decoration-red").as_deref(),
+            Some("text-decoration-color: var(--color-red)")
+        );
+        assert_eq!(
+            generate_color_css(&engine, "divide-red").as_deref(),
+            Some("CHILD|:not(:last-child)|border-color: var(--color-red)")
+        );
+        assert_eq!(
+            generate_color_css(&engine, "ring-red").as_deref(),
+            Some("outline-color: var(--color-red)")
+        );
+        assert!(generate_color_css(&engine, "border-2").is_none());
+        assert!(generate_color_css(&engine, "ring-offset").is_none());
+    }
+}
@@ -94,9 +94,25 @@ pub struct Palettes {
     pub error: TonalPalette,
 }
 
+impl Palettes {
+    /// Looks a palette up by its kebab-case name, e.g. `neutral-variant`.
+    pub fn get(&self, name: &str) -> Option<&TonalPalette> {
+        match name {
+            "primary" => Some(&self.primary),
+            "secondary" => Some(&self.secondary),
+            "tertiary" => Some(&self.tertiary),
+            "neutral" => Some(&self.neutral),
+            "neutral-variant" => Some(&self.neutral_variant),
+            "error" => Some(&self.error),
+            _ => None,
+        }
+    }
+}
+
 pub struct ThemeBuilder {
     source: Argb,
     variant: Variant,
+    contrast_level: Option<f64>,
     color_match: bool,
     primary: Option<Argb>,
     secondary: Option<Argb>,
@@ -114,6 +130,7 @@ impl ThemeBuilder {
         Self {
             source,
             variant: Variant::TonalSpot,
+            contrast_level: None,
             color_match: false,
             primary: None,
             secondary: None,
@@ -133,6 +150,14 @@ impl ThemeBuilder {
         self
     }
 
+    /// Sets the contrast level, from -1.0 (reduced) through 0.0 (default) to 1.0 (high).
+    #[must_use]
+    pub const fn contrast_level(mut self, level: f64) -> Self {
+        self.contrast_level = Some(level);
+
+        self
+    }
+
     /// Sets the primary color of the theme.
     #[must_use]
     pub const fn primary(mut self, color: Argb) -> Self {
@@ -207,8 +232,10 @@ impl ThemeBuilder {
             self.variant = Variant::Fidelity;
         }
 
-        let mut light = DynamicScheme::by_variant(self.source, &self.variant, false, None);
-        let mut dark = DynamicScheme::by_variant(self.source, &self.variant, true, None);
+        let mut light =
+            DynamicScheme::by_variant(self.source, &self.variant, false, self.contrast_level);
+        let mut dark =
+            DynamicScheme::by_variant(self.source, &self.variant, true, self.contrast_level);
 
         if let Some(color) = self.primary {
             let palette = TonalPalette::by_variant(&color.into(), &self.variant, &Palette::Primary);
@@ -255,6 +282,17 @@ impl ThemeBuilder {
             dark.neutral_variant_palette = palette;
         }
 
+        // The palettes the schemes were built from, so tonal steps follow the variant and the
+        // role overrides the way the scheme roles do.
+        let scheme_palettes = Palettes {
+            primary: light.primary_palette,
+            secondary: light.secondary_palette,
+            tertiary: light.tertiary_palette,
+            neutral: light.neutral_palette,
+            neutral_variant: light.neutral_variant_palette,
+            error: light.error_palette,
+        };
+
         Theme {
             source: self.source,
             schemes: Schemes {
@@ -270,6 +308,7 @@ impl ThemeBuilder {
                 neutral_variant: palette.neutral_variant,
                 error: palette.error,
             },
+            scheme_palettes,
             custom_colors: self
                 .custom_colors
                 .into_iter()
@@ -287,5 +326,7 @@ pub struct Theme {
     #[serde(skip_serializing)]
     pub palettes: Palettes,
     #[serde(skip_serializing)]
+    pub scheme_palettes: Palettes,
+    #[serde(skip_serializing)]
     pub custom_colors: Vec<CustomColorGroup>,
 }
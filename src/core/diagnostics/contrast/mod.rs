@@ -0,0 +1,367 @@
+//! Contrast audit of the text and background colors set on the same element.
+//!
+//! Each `class` attribute is checked on its own: when it has both a `bg-*` and a `text-*` color,
+//! the pair is resolved for the light and dark schemes and for every state variant on the element
+//! (`hover:`, `focus:`, ...), letting `dark:hover:text-*` override `hover:text-*` the same way the
+//! cascade does. Pairs below the WCAG ratio or the APCA Lc minimum become diagnostics. Colors with
+//! an opacity modifier, and values the engine cannot compute (`var()`, keywords), are skipped.
+
+use std::fmt;
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+
+use ahash::{AHashMap, AHashSet, AHasher};
+
+use super::{Diagnostic, DiagnosticKind, display_path, line_column};
+use crate::core::color::color::Argb;
+use crate::core::color::contrast::{apca_contrast, ratio_of_colors};
+use crate::core::engine::{StyleEngine, ThemeColors, split_class, split_variants, strip_important};
+use crate::datasource::content::ContentIndex;
+
+/// Minimums from `[diagnostics]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContrastThresholds {
+    pub min_ratio: f64,
+    pub min_lc: f64,
+}
+
+/// A text/background pair that failed one of the minimums.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContrastIssue {
+    pub background: String,
+    pub dark: bool,
+    pub state: Option<String>,
+    pub ratio: f64,
+    /// APCA lightness contrast; negative for light text on a dark background.
+    pub lc: f64,
+}
+
+impl fmt::Display for ContrastIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` ({}",
+            self.background,
+            if self.dark { "dark" } else { "light" }
+        )?;
+        if let Some(state) = &self.state {
+            write!(f, ", {}", state)?;
+        }
+        write!(f, "): {:.2}:1, APCA Lc {:.0}", self.ratio, self.lc.abs())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    Background,
+    Text,
+}
+
+/// One color class of an element with the variants it applies under.
+struct Layer<'a> {
+    class: &'a str,
+    role: Role,
+    name: String,
+    /// `Some(true)` for `dark:`, `Some(false)` for `light:`.
+    scheme: Option<bool>,
+    state: Option<String>,
+}
+
+impl Layer<'_> {
+    fn applies(&self, role: Role, dark: bool, state: Option<&str>) -> bool {
+        self.role == role
+            && self.scheme.is_none_or(|s| s == dark)
+            && self.state.as_deref().is_none_or(|s| Some(s) == state)
+    }
+
+    fn specificity(&self) -> u8 {
+        u8::from(self.scheme.is_some()) + u8::from(self.state.is_some())
+    }
+}
+
+/// Audit results per file, kept until the file's content changes.
+#[derive(Debug, Default)]
+pub struct AuditCache {
+    files: AHashMap<PathBuf, (u64, Vec<Diagnostic>)>,
+}
+
+impl AuditCache {
+    /// Audits the index file (unless `index_bytes` is empty) and every content file, re-checking
+    /// only the files whose content hash changed since the last call.
+    pub fn refresh(
+        &mut self,
+        engine: &StyleEngine,
+        index_path: &Path,
+        index_bytes: &[u8],
+        content: &ContentIndex,
+        thresholds: ContrastThresholds,
+    ) -> Vec<Diagnostic> {
+        let mut seen: AHashSet<PathBuf> = AHashSet::new();
+        if !index_bytes.is_empty() {
+            let mut hasher = AHasher::default();
+            hasher.write(index_bytes);
+            self.check(index_path, hasher.finish(), || {
+                let html = String::from_utf8_lossy(index_bytes);
+                audit(engine, &html, &display_path(index_path), thresholds)
+            });
+            seen.insert(index_path.to_path_buf());
+        }
+        for (path, hash) in content.files() {
+            self.check(path, hash, || {
+                let Ok(bytes) = crate::datasource::read_file(path) else {
+                    return Vec::new();
+                };
+                let elements = content
+                    .extractors()
+                    .for_path(path)
+                    .element_class_lists(&bytes);
+                let source = String::from_utf8_lossy(&bytes);
+                audit_elements(engine, &source, elements, &display_path(path), thresholds)
+            });
+            seen.insert(path.to_path_buf());
+        }
+        self.files.retain(|path, _| seen.contains(path));
+        self.files
+            .values()
+            .flat_map(|(_, found)| found.iter().cloned())
+            .collect()
+    }
+
+    fn check(&mut self, path: &Path, hash: u64, audit: impl FnOnce() -> Vec<Diagnostic>) {
+        if self.files.get(path).is_none_or(|(last, _)| *last != hash) {
+            self.files.insert(path.to_path_buf(), (hash, audit()));
+        }
+    }
+}
+
+/// Checks every element of `html` and returns one diagnostic per failing pair, located at the
+/// element's `class` attribute.
+pub fn audit(
+    engine: &StyleEngine,
+    html: &str,
+    file: &str,
+    thresholds: ContrastThresholds,
+) -> Vec<Diagnostic> {
+    let elements = crate::parser::element_class_lists(html.as_bytes());
+    audit_elements(engine, html, elements, file, thresholds)
+}
+
+/// [`audit`] over class lists already taken from `source`, e.g. by a framework extractor.
+pub fn audit_elements(
+    engine: &StyleEngine,
+    source: &str,
+    elements: Vec<(usize, Vec<String>)>,
+    file: &str,
+    thresholds: ContrastThresholds,
+) -> Vec<Diagnostic> {
+    let colors = engine.theme_colors();
+    let mut out = Vec::new();
+    for (offset, classes) in elements {
+        let layers: Vec<Layer<'_>> = classes
+            .iter()
+            .filter_map(|class| color_layer(engine, &colors, class))
+            .collect();
+        if !layers.iter().any(|l| l.role == Role::Text)
+            || !layers.iter().any(|l| l.role == Role::Background)
+        {
+            continue;
+        }
+        let mut states: Vec<Option<&str>> = vec![None];
+        for state in layers.iter().filter_map(|l| l.state.as_deref()) {
+            if !states.contains(&Some(state)) {
+                states.push(Some(state));
+            }
+        }
+        let (line, column) = line_column(source, offset);
+        // A pair that resolves to the same colors in both schemes is reported once.
+        let mut reported: AHashSet<(&str, &str, [u8; 4], [u8; 4])> = AHashSet::new();
+        for dark in [false, true] {
+            for state in &states {
+                let pick = |role| {
+                    layers
+                        .iter()
+                        .filter(|l| l.applies(role, dark, *state))
+                        .max_by_key(|l| l.specificity())
+                };
+                let (Some(text), Some(background)) = (pick(Role::Text), pick(Role::Background))
+                else {
+                    continue;
+                };
+                let (Some(fg), Some(bg)) = (
+                    colors.resolve(&text.name, dark),
+                    colors.resolve(&background.name, dark),
+                ) else {
+                    continue;
+                };
+                let Some((ratio, lc)) = failing(fg, bg, thresholds) else {
+                    continue;
+                };
+                let rgba = |c: Argb| [c.alpha, c.red, c.green, c.blue];
+                if !reported.insert((text.class, background.class, rgba(fg), rgba(bg))) {
+                    continue;
+                }
+                out.push(Diagnostic {
+                    class: text.class.to_string(),
+                    file: file.to_string(),
+                    line,
+                    column,
+                    kind: DiagnosticKind::LowContrast(ContrastIssue {
+                        background: background.class.to_string(),
+                        dark,
+                        state: state.map(str::to_string),
+                        ratio,
+                        lc,
+                    }),
+                });
+            }
+        }
+    }
+    out
+}
+
+/// WCAG ratio and APCA Lc of the pair when either is below its minimum.
+fn failing(text: Argb, background: Argb, thresholds: ContrastThresholds) -> Option<(f64, f64)> {
+    let ratio = ratio_of_colors(text, background);
+    let lc = apca_contrast(text, background);
+    (ratio < thresholds.min_ratio || lc.abs() < thresholds.min_lc).then_some((ratio, lc))
+}
+
+/// Reads `bg-*`/`text-*` color classes whose variants are `dark:`, `light:` and at most one state.
+fn color_layer<'a>(
+    engine: &StyleEngine,
+    colors: &ThemeColors<'_>,
+    class: &'a str,
+) -> Option<Layer<'a>> {
+    let (lookup, _) = strip_important(class);
+    let (prefix, base) = split_class(&lookup);
+    let found = crate::core::color::match_color_utility(engine, base)?;
+    let role = match found.utility.prefix.as_str() {
+        "bg" => Role::Background,
+        "text" => Role::Text,
+        _ => return None,
+    };
+    if found.alpha.is_some()
+        || crate::core::color::color_reference(engine, found.name).is_none()
+        || colors.resolve(found.name, false).is_none()
+    {
+        return None;
+    }
+    let mut layer = Layer {
+        class,
+        role,
+        name: found.name.to_string(),
+        scheme: None,
+        state: None,
+    };
+    for part in split_variants(prefix).filter(|part| !part.is_empty()) {
+        match part {
+            "dark" | "light" if layer.scheme.is_none() => layer.scheme = Some(part == "dark"),
+            _ if layer.state.is_none() && engine.states.contains_key(part) => {
+                layer.state = Some(part.to_string())
+            }
+            _ => return None,
+        }
+    }
+    Some(layer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRESHOLDS: ContrastThresholds = ContrastThresholds {
+        min_ratio: 4.5,
+        min_lc: 60.0,
+    };
+
+    fn engine() -> StyleEngine {
+        let mut engine = StyleEngine::empty();
+        for (name, value) in [
+            ("paper", "#ffffff"),
+            ("ink", "#111111"),
+            ("mist", "#bbbbbb"),
+            ("night", "#1a1a1a"),
+        ] {
+            engine.colors.insert(name.to_string(), value.to_string());
+        }
+        engine
+            .states
+            .insert("hover".to_string(), ":hover".to_string());
+        engine
+    }
+
+    fn audit_html(engine: &StyleEngine, html: &str) -> Vec<String> {
+        audit(engine, html, "index.html", THRESHOLDS)
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn readable_pairs_pass_and_faint_text_is_located() {
+        let engine = engine();
+        assert!(audit_html(&engine, r#"<p class="bg-paper text-ink"></p>"#).is_empty());
+        assert!(audit_html(&engine, r#"<p class="text-mist"></p>"#).is_empty());
+        assert_eq!(
+            audit_html(
+                &engine,
+                "<div>\n  <p class=\"p-4 bg-paper text-mist\"></p>\n</div>"
+            ),
+            ["index.html:2:13: low contrast `text-mist` on `bg-paper` (light): 1.92:1, APCA Lc 37"]
+        );
+    }
+
+    #[test]
+    fn dark_and_state_variants_override_the_base_pair() {
+        let engine = engine();
+        let fixed = r#"<p class="bg-paper text-ink dark:bg-night dark:text-paper"></p>"#;
+        assert!(audit_html(&engine, fixed).is_empty());
+
+        let html = r#"<p class="bg-paper text-ink dark:bg-night hover:text-mist"></p>"#;
+        let found = audit_html(&engine, html);
+        assert_eq!(found.len(), 2, "{:?}", found);
+        assert!(found[0].contains("`hover:text-mist` on `bg-paper` (light, hover)"));
+        assert!(found[1].contains("`text-ink` on `dark:bg-night` (dark)"));
+    }
+
+    #[test]
+    fn content_files_are_audited_once_per_change() {
+        let engine = engine();
+        let dir = std::env::temp_dir().join(format!("dx_style_audit_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let card = dir.join("Card.tsx");
+        std::fs::write(&card, r#"<p className="bg-paper text-mist" />"#).unwrap();
+        let pattern = format!("{}/*.tsx", dir.to_string_lossy().replace('\\', "/"));
+        let mut content = ContentIndex::new(&[pattern], "index.html");
+        content.refresh(content.globs().walk());
+
+        let mut cache = AuditCache::default();
+        let index = Path::new("index.html");
+        let found = cache.refresh(&engine, index, b"", &content, THRESHOLDS);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].file.ends_with("Card.tsx"));
+        assert_eq!((found[0].line, found[0].column), (1, 15));
+
+        // Results are reused until the content index sees a new hash; an empty index is skipped.
+        std::fs::write(&card, r#"<p className="bg-paper text-ink" />"#).unwrap();
+        assert_eq!(
+            cache.refresh(&engine, index, b"", &content, THRESHOLDS),
+            found
+        );
+        let html = br#"<p class="bg-paper text-mist"></p>"#;
+        content.refresh(content.globs().walk());
+        let found = cache.refresh(&engine, index, html, &content, THRESHOLDS);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].file, "index.html");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn translucent_and_unresolvable_colors_are_skipped() {
+        let engine = engine();
+        assert!(audit_html(&engine, r#"<p class="bg-paper text-mist/50"></p>"#).is_empty());
+        assert!(audit_html(&engine, r#"<p class="bg-paper md:text-mist"></p>"#).is_empty());
+        assert!(audit_html(&engine, r#"<p class="bg-paper text-lg"></p>"#).is_empty());
+    }
+}
@@ -0,0 +1,398 @@
+//! "Unknown utility" diagnostics for classes the engine cannot generate CSS for, and the
+//! contrast audit in [`contrast`].
+//!
+//! Unknown classes are tracked in `AppState::unknown_classes` as the class set changes; locations
+//! and suggestions are only computed for those, so the cost is proportional to the number of typos.
+
+pub mod contrast;
+
+use std::fmt;
+use std::path::Path;
+
+use ahash::AHashSet;
+
+use super::engine::{StyleEngine, split_class, split_variants, states, strip_important};
+use super::group::GroupRegistry;
+use contrast::ContrastIssue;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub class: String,
+    pub file: String,
+    /// 1-based; 0 when the class could not be found in the file.
+    pub line: usize,
+    pub column: usize,
+    pub kind: DiagnosticKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    /// The class generates no CSS.
+    UnknownUtility { suggestion: Option<String> },
+    /// A segment of the class's variant chain is not a variant.
+    UnknownVariant {
+        variant: String,
+        suggestion: Option<String>,
+    },
+    /// The class is a text color that does not contrast enough with the element's background.
+    LowContrast(ContrastIssue),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "{}:{}:{}: ", self.file, self.line, self.column)?;
+        } else {
+            write!(f, "{}: ", self.file)?;
+        }
+        match &self.kind {
+            DiagnosticKind::UnknownUtility { suggestion } => {
+                write!(f, "unknown utility `{}`", self.class)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, " (did you mean `{}`?)", suggestion)?;
+                }
+                Ok(())
+            }
+            DiagnosticKind::UnknownVariant {
+                variant,
+                suggestion,
+            } => {
+                write!(f, "unknown variant `{}` in `{}`", variant, self.class)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, " (did you mean `{}`?)", suggestion)?;
+                }
+                Ok(())
+            }
+            DiagnosticKind::LowContrast(issue) => {
+                write!(f, "low contrast `{}` on {}", self.class, issue)
+            }
+        }
+    }
+}
+
+/// True when the class produces CSS or is handled outside the engine (group aliases and their
+/// internal tokens, animation stages, `group`/`peer` markers).
+pub fn is_known(engine: &StyleEngine, groups: &GroupRegistry, class: &str) -> bool {
+    groups.is_internal_token(class)
+        || crate::core::engine::states::is_relation_marker(class)
+        || groups.is_alias(class)
+        || class.starts_with("from(")
+        || class.starts_with("to(")
+        || class.starts_with("via(")
+        || engine.css_for_class(class).is_some()
+}
+
+/// Line and column (both 1-based, column in characters) of the first standalone occurrence of
+/// `class` in `source`, falling back to the first occurrence of any kind.
+pub fn locate(source: &str, class: &str) -> Option<(usize, usize)> {
+    if class.is_empty() {
+        return None;
+    }
+    let is_boundary = |c: Option<char>| {
+        c.is_none_or(|c| {
+            c.is_whitespace() || matches!(c, '"' | '\'' | '`' | '<' | '>' | '{' | '}' | ',')
+        })
+    };
+    let mut fallback = None;
+    for (pos, _) in source.match_indices(class) {
+        fallback.get_or_insert(pos);
+        let before = source[..pos].chars().next_back();
+        let after = source[pos + class.len()..].chars().next();
+        if is_boundary(before) && is_boundary(after) {
+            return Some(line_column(source, pos));
+        }
+    }
+    fallback.map(|pos| line_column(source, pos))
+}
+
+fn line_column(source: &str, pos: usize) -> (usize, usize) {
+    let before = &source[..pos];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (line, source[line_start..pos].chars().count() + 1)
+}
+
+/// Closest known utility to `class`, keeping its variant prefix. Candidates are the precompiled
+/// names, generator and dynamic prefixes (with the class's own value) and color tokens.
+pub fn suggest(engine: &StyleEngine, class: &str) -> Option<String> {
+    let (variants, base) = split_class(class);
+    let mut best: Option<(usize, String)> = None;
+    let mut consider = |candidate: String| {
+        if candidate == base {
+            return;
+        }
+        let distance = edit_distance(base, &candidate);
+        if distance <= max_distance(base)
+            && best
+                .as_ref()
+                .is_none_or(|(d, c)| distance < *d || (distance == *d && candidate < *c))
+        {
+            best = Some((distance, candidate));
+        }
+    };
+
+    for name in engine.precompiled.keys() {
+        consider(name.clone());
+    }
+    if let Some((stem, value)) = base.rsplit_once('-') {
+        let generator_prefixes = engine
+            .generators
+            .iter()
+            .flatten()
+            .map(|g| g.prefix.as_str());
+        let prefixes = generator_prefixes
+            .chain(engine.dynamic_properties.keys().map(String::as_str))
+            .chain(engine.color_utilities.iter().map(|u| u.prefix.as_str()));
+        for prefix in prefixes {
+            if !prefix.is_empty() && prefix != stem {
+                consider(format!("{}-{}", prefix, value));
+            }
+        }
+    }
+    if let Some(found) = crate::core::color::match_color_utility(engine, base) {
+        let prefix = &found.utility.prefix;
+        let theme_tokens = engine
+            .themes
+            .iter()
+            .flat_map(|t| t.tokens.iter().map(|(name, _)| name.as_str()));
+        let mut seen = AHashSet::new();
+        for token in engine.colors.keys().map(String::as_str).chain(theme_tokens) {
+            if seen.insert(token) {
+                consider(format!("{}-{}", prefix, token));
+            }
+        }
+        for keyword in crate::core::color::css_color_keywords() {
+            consider(format!("{}-{}", prefix, keyword));
+        }
+    }
+
+    best.map(|(_, candidate)| {
+        if variants.is_empty() {
+            candidate
+        } else {
+            format!("{}:{}", variants, candidate)
+        }
+    })
+}
+
+/// `class` with its unknown `variant` replaced by the closest variant name; `group-`/`peer-`
+/// variants are matched on their state.
+pub fn suggest_variant(engine: &StyleEngine, class: &str, variant: &str) -> Option<String> {
+    let (relation, state) = ["group-", "peer-"]
+        .iter()
+        .find_map(|prefix| Some((*prefix, variant.strip_prefix(prefix)?)))
+        .unwrap_or(("", variant));
+    let (state, name) = match state.split_once('/') {
+        Some((state, name)) if !relation.is_empty() => (state, Some(name)),
+        _ => (state, None),
+    };
+    let replacement = states::variant_names(engine)
+        .filter(|candidate| *candidate != state)
+        .map(|candidate| (edit_distance(state, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance(state))
+        .min()
+        .map(|(_, candidate)| match name {
+            Some(name) => format!("{}{}/{}", relation, candidate, name),
+            None => format!("{}{}", relation, candidate),
+        })?;
+    let (lookup, _) = strip_important(class);
+    let (variants, base) = split_class(&lookup);
+    let mut replaced = false;
+    let parts: Vec<&str> = split_variants(variants)
+        .map(|part| {
+            if !replaced && part == variant {
+                replaced = true;
+                replacement.as_str()
+            } else {
+                part
+            }
+        })
+        .collect();
+    Some(format!("{}:{}", parts.join(":"), base))
+}
+
+fn max_distance(word: &str) -> usize {
+    (word.chars().count() / 3).clamp(1, 3)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+/// Builds a located diagnostic for every class in `unknown`. `source_for` returns the file a
+/// class came from together with its contents.
+pub fn collect<'a, I, F>(engine: &StyleEngine, unknown: I, mut source_for: F) -> Vec<Diagnostic>
+where
+    I: IntoIterator<Item = &'a String>,
+    F: FnMut(&str) -> Option<(String, String)>,
+{
+    let mut out: Vec<Diagnostic> = unknown
+        .into_iter()
+        .map(|class| {
+            let (file, (line, column)) = match source_for(class) {
+                Some((file, source)) => {
+                    let location = locate(&source, class).unwrap_or((0, 0));
+                    (file, location)
+                }
+                None => (String::new(), (0, 0)),
+            };
+            let (lookup, _) = strip_important(class);
+            let kind = match states::unknown_variant(engine, split_class(&lookup).0) {
+                Some(variant) => DiagnosticKind::UnknownVariant {
+                    variant: variant.to_string(),
+                    suggestion: suggest_variant(engine, class, variant),
+                },
+                None => DiagnosticKind::UnknownUtility {
+                    suggestion: suggest(engine, class),
+                },
+            };
+            Diagnostic {
+                class: class.clone(),
+                file,
+                line,
+                column,
+                kind,
+            }
+        })
+        .collect();
+    sort(&mut out);
+    out
+}
+
+/// Orders diagnostics by file, position and class.
+pub fn sort(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by(|a, b| {
+        (&a.file, a.line, a.column, &a.class).cmp(&(&b.file, b.line, b.column, &b.class))
+    });
+}
+
+/// Prints diagnostics that were not printed by the previous call, so a watch session reports each
+/// typo once instead of on every rebuild.
+#[derive(Debug, Default)]
+pub struct Reporter {
+    printed: AHashSet<String>,
+}
+
+impl Reporter {
+    pub fn report(&mut self, diagnostics: &[Diagnostic]) {
+        use colored::Colorize;
+        let current: AHashSet<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        for diagnostic in diagnostics {
+            let line = diagnostic.to_string();
+            if !self.printed.contains(&line) {
+                eprintln!("{} {}", "warning:".yellow(), line);
+            }
+        }
+        self.printed = current;
+    }
+}
+
+/// Path used in diagnostics: relative to the working directory when possible.
+pub fn display_path(path: &Path) -> String {
+    crate::datasource::glob::relative_key(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::GeneratorMeta;
+
+    #[test]
+    fn locate_reports_line_and_column_of_standalone_class() {
+        let html = "<div class=\"flex\">\n  <p class=\"p-4 flexx\"></p>\n</div>";
+        assert_eq!(locate(html, "flexx"), Some((2, 17)));
+        assert_eq!(locate(html, "flex"), Some((1, 13)));
+        assert_eq!(locate(html, "grid"), None);
+    }
+
+    #[test]
+    fn suggestions_cover_precompiled_generators_and_colors() {
+        let mut engine = StyleEngine::empty();
+        engine.precompiled.insert(
+            "items-center".to_string(),
+            "align-items: center".to_string(),
+        );
+        engine.generators = Some(vec![GeneratorMeta {
+            prefix: "px".to_string(),
+            property: "padding-inline".to_string(),
+            multiplier: 0.25,
+            unit: "rem".to_string(),
+        }]);
+        engine
+            .colors
+            .insert("primary".to_string(), "#6750a4".to_string());
+
+        assert_eq!(
+            suggest(&engine, "md:itmes-center").as_deref(),
+            Some("md:items-center")
+        );
+        assert_eq!(suggest(&engine, "pz-4").as_deref(), Some("px-4"));
+        assert_eq!(suggest(&engine, "bg-primar").as_deref(), Some("bg-primary"));
+        assert_eq!(suggest(&engine, "text-gren").as_deref(), Some("text-green"));
+        assert_eq!(suggest(&engine, "completely-unrelated"), None);
+
+        let diagnostic = Diagnostic {
+            class: "bg-primar".to_string(),
+            file: "index.html".to_string(),
+            line: 3,
+            column: 14,
+            kind: DiagnosticKind::UnknownUtility {
+                suggestion: Some("bg-primary".to_string()),
+            },
+        };
+        assert_eq!(
+            diagnostic.to_string(),
+            "index.html:3:14: unknown utility `bg-primar` (did you mean `bg-primary`?)"
+        );
+    }
+
+    #[test]
+    fn misspelled_variants_are_unknown_and_corrected() {
+        let mut engine = StyleEngine::empty();
+        engine
+            .precompiled
+            .insert("flex".to_string(), "display: flex".to_string());
+        engine
+            .states
+            .insert("hover".to_string(), ":hover".to_string());
+        engine.screens.insert("md".to_string(), "768px".to_string());
+        let groups = GroupRegistry::new();
+        assert!(is_known(&engine, &groups, "md:hover:flex"));
+        assert!(!is_known(&engine, &groups, "hovr:flex"));
+        assert!(engine.css_for_class("hovr:flex").is_none());
+
+        let unknown: Vec<String> = [
+            "md:hovr:flex",
+            "group-hovr/card:flex",
+            "dak:flex",
+            "xyzzy:flex",
+        ]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+        let found: Vec<String> = collect(&engine, &unknown, |_| None)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ": unknown variant `dak` in `dak:flex` (did you mean `dark:flex`?)",
+                ": unknown variant `group-hovr/card` in `group-hovr/card:flex` (did you mean `group-hover/card:flex`?)",
+                ": unknown variant `hovr` in `md:hovr:flex` (did you mean `md:hover:flex`?)",
+                ": unknown variant `xyzzy` in `xyzzy:flex`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use std::borrow::Cow;
+
+use lightningcss::properties::{Property, PropertyId};
+use lightningcss::stylesheet::ParserOptions;
+
+use crate::core::engine::StyleEngine;
+
+/// Properties for prefixes that are neither generators nor dynamic keys.
+const FALLBACK_PROPERTIES: &[(&str, &str)] = &[
+    ("bg", "background"),
+    ("text", "font-size"),
+    ("border", "border-width"),
+    ("content", "content, --dx-content"),
+];
+
+/// Splits `md:hover:w-[37px]` into (`md:hover`, `w-[37px]`), ignoring colons inside brackets
+/// such as `[mask-type:luminance]`.
+pub fn split_class(class_name: &str) -> (&str, &str) {
+    let mut depth = 0i32;
+    let mut last_colon = None;
+    for (i, b) in class_name.bytes().enumerate() {
+        match b {
+            b'[' => depth += 1,
+            b']' => depth -= 1,
+            b':' if depth == 0 => last_colon = Some(i),
+            _ => {}
+        }
+    }
+    match last_colon {
+        Some(idx) => (&class_name[..idx], &class_name[idx + 1..]),
+        None => ("", class_name),
+    }
+}
+
+/// Removes the important marker. `!p-4`, `md:!p-4`, `!md:p-4` and `p-4!` all resolve `p-4`
+/// (keeping the variants) and report `true`.
+pub fn strip_important(class_name: &str) -> (Cow<'_, str>, bool) {
+    let (prefix, base) = split_class(class_name);
+    let bare_prefix = prefix.strip_prefix('!');
+    let bare_base = base
+        .strip_prefix('!')
+        .or_else(|| base.strip_suffix('!'))
+        .filter(|b| !b.is_empty());
+    if bare_prefix.is_none() && bare_base.is_none() {
+        return (Cow::Borrowed(class_name), false);
+    }
+    let base = bare_base.unwrap_or(base);
+    let stripped = match bare_prefix.unwrap_or(prefix) {
+        "" if prefix.is_empty() => Cow::Borrowed(base),
+        "" => return (Cow::Borrowed(class_name), false),
+        prefix => Cow::Owned(format!("{}:{}", prefix, base)),
+    };
+    (stripped, true)
+}
+
+/// Splits a variant chain on top-level colons.
+pub fn split_variants(prefix_segment: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0i32;
+    prefix_segment.split(move |c: char| {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        c == ':' && depth == 0
+    })
+}
+
+/// Turns the inside of `[...]` into a CSS value: `_` becomes a space, `\_` a literal underscore.
+pub fn decode_value(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' if chars.peek() == Some(&'_') => {
+                out.push('_');
+                chars.next();
+            }
+            '_' => out.push(' '),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Checks a declaration with lightningcss. Values that only parse as an unparsed token stream
+/// are rejected unless they reference `var()`/`env()`, which can't be checked ahead of time.
+pub fn is_valid_declaration(property: &str, value: &str) -> bool {
+    let value = value.trim();
+    if value.is_empty() || value.contains([';', '{', '}']) {
+        return false;
+    }
+    match Property::parse_string(PropertyId::from(property), value, ParserOptions::default()) {
+        Ok(Property::Unparsed(_)) => value.contains("var(") || value.contains("env("),
+        Ok(_) => true,
+        Err(_) => false,
+    }
+}
+
+fn is_property_name(name: &str) -> bool {
+    let body = name.strip_prefix("--").unwrap_or(name);
+    !body.is_empty()
+        && body.starts_with(|c: char| c.is_ascii_alphabetic() || name.starts_with("--"))
+        && body
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+fn looks_like_color(value: &str) -> bool {
+    value.starts_with('#')
+        || crate::core::color::parse_color_to_argb(value).is_some()
+        || matches!(
+            value.to_ascii_lowercase().as_str(),
+            "transparent" | "currentcolor"
+        )
+        || ["oklch(", "oklab(", "lab(", "lch(", "color-mix(", "hwb("]
+            .iter()
+            .any(|f| value.starts_with(f))
+}
+
+/// Resolves the property an arbitrary `prefix-[value]` should set, and whether it is one of the
+/// configured color utilities (only those accept an alpha modifier).
+fn property_for(engine: &StyleEngine, prefix: &str, value: &str) -> Option<(String, String, bool)> {
+    let (hint, value) = match value.split_once(':') {
+        Some((hint @ ("color" | "length"), rest)) => (Some(hint), rest.to_string()),
+        _ => (None, value.to_string()),
+    };
+    if hint != Some("length")
+        && let Some(utility) = engine
+            .color_utilities
+            .iter()
+            .find(|u| u.prefix == prefix && u.selector.is_none())
+        && (hint == Some("color") || looks_like_color(&value))
+    {
+        return Some((utility.properties.join(","), value, true));
+    }
+    if let (Some(generators), Some(map)) = (&engine.generators, &engine.generator_map)
+        && let Some(g) = map.get(prefix).and_then(|idx| generators.get(*idx))
+    {
+        return Some((g.property.clone(), value, false));
+    }
+    if let Some(property) = engine.dynamic_properties.get(prefix) {
+        return Some((property.clone(), value, false));
+    }
+    FALLBACK_PROPERTIES
+        .iter()
+        .find(|(p, _)| *p == prefix)
+        .map(|(_, property)| (property.to_string(), value, false))
+}
+
+/// Generates declarations for `w-[37px]`, `bg-[#1e293b]` and `[mask-type:luminance]`.
+pub fn generate_arbitrary_css(engine: &StyleEngine, class_name: &str) -> Option<String> {
+    let (class_name, alpha) = match class_name.rfind("]/") {
+        Some(i) => (
+            &class_name[..=i],
+            Some(crate::core::color::parse_alpha(&class_name[i + 2..])?),
+        ),
+        None => (class_name, None),
+    };
+    let raw = class_name.strip_suffix(']')?;
+    if let Some(inner) = raw.strip_prefix('[') {
+        if alpha.is_some() {
+            return None;
+        }
+        let (property, value) = inner.split_once(':')?;
+        if !is_property_name(property) {
+            return None;
+        }
+        let value = decode_value(value);
+        if !is_valid_declaration(property, &value) {
+            return None;
+        }
+        return Some(format!("{}: {};", property, value));
+    }
+    let open = raw.find("-[")?;
+    let prefix = &raw[..open];
+    let value = decode_value(&raw[open + 2..]);
+    if prefix.is_empty() || value.is_empty() {
+        return None;
+    }
+    let (property, mut value, is_color) = property_for(engine, prefix, &value)?;
+    if let Some(alpha) = alpha {
+        if !is_color {
+            return None;
+        }
+        value = crate::core::color::apply_alpha(&value, Some(&alpha));
+    }
+    let properties: Vec<&str> = property
+        .split(',')
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .collect();
+    if properties.is_empty()
+        || !properties
+            .iter()
+            .all(|property| is_valid_declaration(property, &value))
+    {
+        return None;
+    }
+    let mut out = String::new();
+    for (i, p) in properties.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        out.push_str(p);
+        out.push_str(": ");
+        out.push_str(&value);
+        out.push(';');
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::engine::GeneratorMeta;
+    use ahash::AHashMap;
+
+    fn engine() -> StyleEngine {
+        let mut engine = StyleEngine::empty();
+        engine.generators = Some(vec![GeneratorMeta {
+            prefix: "w".to_string(),
+            property: "width".to_string(),
+            multiplier: 0.25,
+            unit: "rem".to_string(),
+        }]);
+        let mut map = AHashMap::new();
+        map.insert("w".to_string(), 0);
+        engine.generator_map = Some(map);
+        engine
+            .dynamic_properties
+            .insert("leading".to_string(), "line-height".to_string());
+        engine
+    }
+
+    #[test]
+    fn split_ignores_colons_inside_brackets() {
+        assert_eq!(
+            split_class("md:[mask-type:luminance]"),
+            ("md", "[mask-type:luminance]")
+        );
+        assert_eq!(split_class("hover:w-[37px]"), ("hover", "w-[37px]"));
+        assert_eq!(
+            split_variants("md:data-[a:b]:hover").collect::<Vec<_>>(),
+            vec!["md", "data-[a:b]", "hover"]
+        );
+    }
+
+    #[test]
+    fn important_marker_is_stripped_before_or_after_the_utility() {
+        assert_eq!(strip_important("!p-4"), (Cow::Borrowed("p-4"), true));
+        assert_eq!(strip_important("p-4!"), (Cow::Borrowed("p-4"), true));
+        assert_eq!(strip_important("md:!p-4").0, "md:p-4");
+        assert_eq!(strip_important("!md:hover:p-4").0, "md:hover:p-4");
+        assert_eq!(strip_important("p-4"), (Cow::Borrowed("p-4"), false));
+        assert_eq!(strip_important("!"), (Cow::Borrowed("!"), false));
+    }
+
+    #[test]
+    fn arbitrary_values_infer_property_from_prefix() {
+        let engine = engine();
+        assert_eq!(
+            generate_arbitrary_css(&engine, "w-[37px]").as_deref(),
+            Some("width: 37px;")
+        );
+        assert_eq!(
+            generate_arbitrary_css(&engine, "leading-[1.35]").as_deref(),
+            Some("line-height: 1.35;")
+        );
+        assert_eq!(
+            generate_arbitrary_css(&engine, "bg-[#1e293b]").as_deref(),
+            Some("background-color: #1e293b;")
+        );
+        assert_eq!(
+            generate_arbitrary_css(&engine, "w-[calc(100%_-_2rem)]").as_deref(),
+            Some("width: calc(100% - 2rem);")
+        );
+        assert_eq!(
+            generate_arbitrary_css(&engine, "[mask-type:luminance]").as_deref(),
+            Some("mask-type: luminance;")
+        );
+        assert_eq!(
+            generate_arbitrary_css(&engine, "content-['→_next']").as_deref(),
+            Some("content: '→ next'; --dx-content: '→ next';")
+        );
+    }
+
+    #[test]
+    fn arbitrary_colors_accept_alpha_modifier() {
+        let engine = engine();
+        assert_eq!(
+            generate_arbitrary_css(&engine, "bg-[#1e293b]/50").as_deref(),
+            Some("background-color: color-mix(in oklch, #1e293b 50%, transparent);")
+        );
+        assert!(generate_arbitrary_css(&engine, "w-[37px]/50").is_none());
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let engine = engine();
+        assert!(generate_arbitrary_css(&engine, "w-[banana]").is_none());
+        assert!(generate_arbitrary_css(&engine, "w-[1px;color:red]").is_none());
+        assert!(generate_arbitrary_css(&engine, "[mask-type:sideways]").is_none());
+        assert!(generate_arbitrary_css(&engine, "nope-[1px]").is_none());
+
+        let mut engine = engine;
+        engine
+            .dynamic_properties
+            .insert("frame".to_string(), "width, mask-type".to_string());
+        assert!(generate_arbitrary_css(&engine, "frame-[10px]").is_none());
+    }
+
+    #[test]
+    fn selector_is_escaped() {
+        let mut engine = engine();
+        engine.screens.insert("md".to_string(), "768px".to_string());
+        let css = engine.compute_css("md:bg-[#1e293b]").unwrap_or_default();
+        assert!(css.contains(".md\\:bg-\\[\\#1e293b\\]"), "{css}");
+    }
+}
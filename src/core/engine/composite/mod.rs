@@ -1,5 +1,13 @@
+//! Composites: one class name that expands to several rules, defined in `composites.toml`.
+//!
+//! Each rule lists utilities that are resolved like ordinary classes, so a composite can style
+//! its children (`space-x-4`), its own states (`btn`) or emit raw nested rules (`prose`).
+
 use std::collections::BTreeMap;
 
+use crate::core::engine::StyleEngine;
+
+#[derive(Clone, Debug, Default)]
 pub struct Composite {
     pub base: Vec<String>,
     pub child_rules: BTreeMap<String, Vec<String>>,
@@ -10,26 +18,48 @@ pub struct Composite {
     pub extra_raw: Vec<String>,
 }
 
-#[allow(dead_code)]
-pub fn get(_name: &str) -> Option<Composite> {
-    None
+impl Composite {
+    /// Builds a composite from `composites.toml` fields, splitting each token list on whitespace.
+    pub fn from_rules(
+        base: &str,
+        child: &[(String, String)],
+        state: &[(String, String)],
+        data: &[(String, String)],
+        conditional: &[(String, String)],
+        raw: Vec<String>,
+    ) -> Self {
+        let tokens =
+            |list: &str| -> Vec<String> { list.split_whitespace().map(str::to_string).collect() };
+        let rules = |pairs: &[(String, String)]| -> BTreeMap<String, Vec<String>> {
+            pairs
+                .iter()
+                .map(|(key, list)| (key.clone(), tokens(list)))
+                .filter(|(key, toks)| !key.is_empty() && !toks.is_empty())
+                .collect()
+        };
+        Self {
+            base: tokens(base),
+            child_rules: rules(child),
+            state_rules: rules(state),
+            data_attr_rules: rules(data),
+            conditional_blocks: rules(conditional),
+            animations: Vec::new(),
+            extra_raw: raw,
+        }
+    }
 }
 
-#[allow(dead_code)]
-pub fn get_composite_types_defined() -> usize {
-    0
+pub fn get<'a>(engine: &'a StyleEngine, name: &str) -> Option<&'a Composite> {
+    engine.composites.get(name)
 }
 
-use crate::core::engine::StyleEngine;
+#[allow(dead_code)]
+pub fn get_composite_types_defined(engine: &StyleEngine) -> usize {
+    engine.composites.len()
+}
 
 pub fn expand_composite(engine: &StyleEngine, class_name: &str) -> Option<String> {
-    let comp = if let Some(c) = get(class_name) {
-        c
-    } else if class_name.starts_with("dx-class-") {
-        get(class_name)?
-    } else {
-        return None;
-    };
+    let comp = get(engine, class_name)?;
     let resolve_tokens = |tokens: &[String]| -> (Vec<String>, Vec<String>) {
         let mut base_rules: Vec<String> = Vec::new();
         let mut anim_lines: Vec<String> = Vec::new();
@@ -38,6 +68,10 @@ pub fn expand_composite(engine: &StyleEngine, class_name: &str) -> Option<String
                 base_rules.push(rule.clone());
                 continue;
             }
+            if let Some(a) = crate::core::engine::generate_arbitrary_css(engine, t) {
+                base_rules.push(a);
+                continue;
+            }
             if let Some(c) = crate::core::color::generate_color_css(engine, t) {
                 base_rules.push(c);
                 continue;
@@ -110,10 +144,144 @@ pub fn expand_composite(engine: &StyleEngine, class_name: &str) -> Option<String
         sections.push(format!("ANIM|{}", anim));
     }
     for raw in &comp.extra_raw {
-        sections.push(format!("RAW|{}", raw));
+        for rule in top_level_rules(raw) {
+            sections.push(format!("RAW|{}", rule));
+        }
     }
     if sections.is_empty() {
         return None;
     }
     Some(sections.join("\n"))
 }
+
+/// Splits a `raw` entry into its top-level rules, each on one line, so a rule spread over several
+/// lines is still expanded as a whole for every selector the class applies to.
+fn top_level_rules(raw: &str) -> Vec<String> {
+    let mut rules = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    for ch in raw.chars() {
+        current.push(ch);
+        match ch {
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    rules.push(current.split_whitespace().collect::<Vec<_>>().join(" "));
+                    current.clear();
+                }
+            }
+            _ => {}
+        }
+    }
+    let rest = current.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !rest.is_empty() {
+        rules.push(rest);
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(list: &[(&str, &str)]) -> Vec<(String, String)> {
+        list.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn engine() -> StyleEngine {
+        let mut engine = StyleEngine::empty();
+        for (name, css) in [
+            ("px-4", "padding-inline: 1rem"),
+            ("rounded", "border-radius: 0.25rem"),
+            ("opacity-80", "opacity: 0.8"),
+            ("max-w-prose", "max-width: 65ch"),
+        ] {
+            engine.precompiled.insert(name.to_string(), css.to_string());
+        }
+        engine
+            .states
+            .insert("hover".to_string(), ":hover".to_string());
+        engine.screens.insert("md".to_string(), "768px".to_string());
+        engine.composites.insert(
+            "btn".to_string(),
+            Composite::from_rules(
+                "px-4 rounded",
+                &[],
+                &pairs(&[("hover", "opacity-80")]),
+                &pairs(&[("disabled", "opacity-80")]),
+                &[],
+                Vec::new(),
+            ),
+        );
+        engine.composites.insert(
+            "space-x-4".to_string(),
+            Composite::from_rules(
+                "",
+                &pairs(&[(":not(:last-child)", "[margin-inline-end:1rem]")]),
+                &[],
+                &[],
+                &[],
+                Vec::new(),
+            ),
+        );
+        engine.composites.insert(
+            "prose".to_string(),
+            Composite::from_rules(
+                "max-w-prose",
+                &[],
+                &[],
+                &[],
+                &[],
+                vec!["& h1 { font-size: 2.25em; }\n& p { margin-block: 1.25em; }".to_string()],
+            ),
+        );
+        engine
+    }
+
+    #[test]
+    fn state_and_data_rules_follow_the_class() {
+        let engine = engine();
+        let css = engine.css_for_class("btn").unwrap();
+        assert!(css.contains(".btn {\n  padding-inline: 1rem;\n  border-radius: 0.25rem;\n}"));
+        assert!(css.contains(".btn:hover {\n  opacity: 0.8;\n}"));
+        assert!(css.contains(".btn[data-disabled] {\n  opacity: 0.8;\n}"));
+        let css = engine.css_for_class("md:btn").unwrap();
+        assert!(css.starts_with("@media (min-width: 768px)"));
+        assert!(css.contains(".md\\:btn:hover {"));
+    }
+
+    #[test]
+    fn child_and_raw_rules_use_the_class_selector() {
+        let engine = engine();
+        let css = engine.css_for_class("space-x-4").unwrap();
+        assert!(css.contains(".space-x-4 > :not(:last-child) {\n  margin-inline-end: 1rem;\n}"));
+        let css = engine.css_for_class("prose").unwrap();
+        assert!(css.contains(".prose {\n  max-width: 65ch;\n}"));
+        assert!(css.contains(".prose h1 {\n  font-size: 2.25em;\n}"));
+        assert!(css.contains(".prose p {\n  margin-block: 1.25em;\n}"));
+        assert!(engine.css_for_class("card").is_none());
+    }
+
+    #[test]
+    fn raw_rules_follow_variants_and_importance() {
+        let mut engine = engine();
+        engine
+            .states
+            .insert("open".to_string(), "&[open]".to_string());
+        let css = engine.css_for_class("open:prose").unwrap();
+        assert!(css.contains(".open\\:prose[open] h1 {"), "{css}");
+        assert!(!css.contains(".open\\:prose h1"), "{css}");
+        let css = engine.css_for_class("!prose").unwrap();
+        assert!(css.contains("\\!prose h1 {\n  font-size: 2.25em !important;\n}"));
+        assert_eq!(
+            top_level_rules("& pre {\n  padding: 1em;\n}\n@media print { & pre { margin: 0; } }"),
+            [
+                "& pre { padding: 1em; }",
+                "@media print { & pre { margin: 0; } }"
+            ]
+        );
+    }
+}
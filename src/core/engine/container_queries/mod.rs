@@ -1,81 +1,196 @@
+//! Container queries: `@container` utilities and `@md:`-style variants.
+//!
+//! Sizes come from `container_queries.toml`, whose keys may be written with or without the
+//! leading `@`. A variant targets the nearest container unless it names one after a slash.
+
 use crate::core::engine::StyleEngine;
+use crate::core::engine::screens::{MediaVariant, exclusive_max, length_px};
 
-#[allow(dead_code)]
-pub fn generate_container_group(
-    engine: &StyleEngine,
-    raw: &str,
-    escaped_selector: &str,
-) -> Option<String> {
-    if !raw.starts_with("?@container>") {
-        return None;
-    }
-    const PREFIX: &str = "?@container>";
-    let after_prefix = &raw[PREFIX.len()..];
-    let paren_idx = after_prefix.find('(')?;
-    let size_part = after_prefix[..paren_idx].trim();
-    if size_part.is_empty() {
-        return None;
-    }
-    let inner_raw = after_prefix[paren_idx + 1..].strip_suffix(')')?;
-    let size_expr = if size_part.chars().all(|c| c.is_ascii_digit()) {
-        format!("{}px", size_part)
+/// One container-query variant: the bound and the container it applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerVariant {
+    pub name: Option<String>,
+    pub bound: MediaVariant,
+}
+
+/// Resolves `@md`, `@md/sidebar`, `@[400px]`, `@max-md` and `@max-[400px]/sidebar`.
+pub fn container_variant(engine: &StyleEngine, part: &str) -> Option<ContainerVariant> {
+    let rest = part.strip_prefix('@')?;
+    let (size, name) = match rest.rsplit_once('/') {
+        Some((_, name)) if !name.contains(']') && !is_container_name(name) => return None,
+        Some((size, name)) if !name.contains(']') => (size, Some(name)),
+        _ => (rest, None),
+    };
+    let (size, max) = match size.strip_prefix("max-") {
+        Some(size) => (size, true),
+        None => (size, false),
+    };
+    let bound = if let Some(inner) = size.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        let value = crate::core::engine::arbitrary::decode_value(inner);
+        length_px(&value)?;
+        if max {
+            MediaVariant::Max(value)
+        } else {
+            MediaVariant::Min(value)
+        }
     } else {
-        size_part.to_string()
+        let value = size_value(engine, size)?;
+        if max {
+            MediaVariant::Max(exclusive_max(value))
+        } else {
+            MediaVariant::Min(value.to_string())
+        }
     };
-    let inner_utils: Vec<&str> = inner_raw
-        .split(|c: char| c.is_whitespace())
-        .filter(|s| !s.is_empty())
-        .collect();
-    if inner_utils.is_empty() {
+    Some(ContainerVariant {
+        name: name.map(str::to_string),
+        bound,
+    })
+}
+
+fn size_value<'a>(engine: &'a StyleEngine, size: &str) -> Option<&'a str> {
+    if size.is_empty() {
         return None;
     }
-    use ahash::AHashMap;
-    let mut decls: AHashMap<String, (usize, String)> = AHashMap::new();
-    let mut order: usize = 0;
-    for util in &inner_utils {
-        if let Some(raw_css) = engine.compute_css(util) {
-            if let Some(open) = raw_css.find('{') {
-                if let Some(close) = raw_css.find('}') {
-                    if close > open {
-                        let body = &raw_css[open + 1..close];
-                        for seg in body.split(';') {
-                            let seg = seg.trim();
-                            if seg.is_empty() {
-                                continue;
-                            }
-                            if let Some(colon) = seg.find(':') {
-                                let prop = seg[..colon].trim().to_string();
-                                let value =
-                                    seg[colon + 1..].trim().trim_end_matches(';').to_string();
-                                decls.insert(prop, (order, value));
-                                order += 1;
-                            }
-                        }
-                    }
-                }
-            }
+    engine
+        .container_queries
+        .get(&format!("@{}", size))
+        .or_else(|| engine.container_queries.get(size))
+        .map(String::as_str)
+}
+
+/// `@container` rules for a variant chain. Bounds on the same container share one rule
+/// (`@md:@max-lg:`); different containers nest in the order they were written.
+pub fn container_queries(variants: &[ContainerVariant]) -> Vec<String> {
+    let mut groups: Vec<(Option<&str>, Vec<String>)> = Vec::new();
+    for variant in variants {
+        let condition = match &variant.bound {
+            MediaVariant::Min(v) => format!("(min-width: {})", v),
+            MediaVariant::Max(v) => format!("(max-width: {})", v),
+            _ => continue,
+        };
+        let name = variant.name.as_deref();
+        match groups.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, conditions)) => conditions.push(condition),
+            None => groups.push((name, vec![condition])),
         }
     }
-    if decls.is_empty() {
-        return None;
+    groups
+        .into_iter()
+        .map(|(name, conditions)| match name {
+            Some(name) => format!("@container {} {}", name, conditions.join(" and ")),
+            None => format!("@container {}", conditions.join(" and ")),
+        })
+        .collect()
+}
+
+/// `@container` and `@container/sidebar` mark an element as an inline-size query container;
+/// `@container-normal` makes it a style-only container.
+pub fn generate_container_utility(base_class: &str) -> Option<String> {
+    let rest = base_class.strip_prefix("@container")?;
+    let (container_type, name) = match rest.strip_prefix("-normal") {
+        Some(name) => ("normal", name),
+        None => ("inline-size", rest),
+    };
+    match name.strip_prefix('/') {
+        Some(name) if is_container_name(name) => Some(format!(
+            "container-type: {}; container-name: {};",
+            container_type, name
+        )),
+        Some(_) => None,
+        None if name.is_empty() => Some(format!("container-type: {};", container_type)),
+        None => None,
     }
-    let mut ordered: Vec<(String, (usize, String))> = decls.into_iter().collect();
-    ordered.sort_by_key(|(_, (ord, _))| *ord);
-    let mut body = String::new();
-    for (prop, (_, val)) in ordered {
-        body.push_str("    ");
-        body.push_str(&prop);
-        body.push_str(": ");
-        body.push_str(&val);
-        body.push_str(";\n");
+}
+
+fn is_container_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> StyleEngine {
+        let mut engine = StyleEngine::empty();
+        engine
+            .container_queries
+            .insert("@md".to_string(), "28rem".to_string());
+        engine
+            .container_queries
+            .insert("lg".to_string(), "32rem".to_string());
+        engine
+            .precompiled
+            .insert("flex".to_string(), "display: flex".to_string());
+        engine.screens.insert("sm".to_string(), "640px".to_string());
+        engine
+    }
+
+    #[test]
+    fn container_utilities_set_type_and_name() {
+        assert_eq!(
+            generate_container_utility("@container").as_deref(),
+            Some("container-type: inline-size;")
+        );
+        assert_eq!(
+            generate_container_utility("@container/sidebar").as_deref(),
+            Some("container-type: inline-size; container-name: sidebar;")
+        );
+        assert_eq!(
+            generate_container_utility("@container-normal").as_deref(),
+            Some("container-type: normal;")
+        );
+        assert_eq!(generate_container_utility("@container/9x"), None);
+        assert_eq!(generate_container_utility("@containers"), None);
+    }
+
+    #[test]
+    fn variants_resolve_named_arbitrary_and_max_sizes() {
+        let engine = engine();
+        let queries = |parts: &[&str]| {
+            let variants: Vec<ContainerVariant> = parts
+                .iter()
+                .map(|p| container_variant(&engine, p).unwrap())
+                .collect();
+            container_queries(&variants)
+        };
+        assert_eq!(queries(&["@md"]), ["@container (min-width: 28rem)"]);
+        assert_eq!(
+            queries(&["@md/sidebar"]),
+            ["@container sidebar (min-width: 28rem)"]
+        );
+        assert_eq!(queries(&["@[400px]"]), ["@container (min-width: 400px)"]);
+        assert_eq!(queries(&["@max-md"]), ["@container (max-width: 447.98px)"]);
+        assert_eq!(
+            queries(&["@md", "@max-lg"]),
+            ["@container (min-width: 28rem) and (max-width: 511.98px)"]
+        );
+        assert_eq!(
+            queries(&["@md/sidebar", "@lg"]),
+            [
+                "@container sidebar (min-width: 28rem)",
+                "@container (min-width: 32rem)"
+            ]
+        );
+        assert_eq!(container_variant(&engine, "@xl"), None);
+        assert_eq!(container_variant(&engine, "@[wide]"), None);
+        assert_eq!(container_variant(&engine, "@md/9x"), None);
+        assert_eq!(container_variant(&engine, "@md/a)or(b"), None);
+        assert_eq!(container_variant(&engine, "@md/"), None);
+    }
+
+    #[test]
+    fn media_wraps_container_queries() {
+        let engine = engine();
+        let css = engine.css_for_class("sm:@md/sidebar:flex").unwrap();
+        assert!(css.starts_with(
+            "@media (min-width: 640px) {\n  @container sidebar (min-width: 28rem) {\n    .sm\\:\\@md\\/sidebar\\:flex {"
+        ));
+        let css = engine.css_for_class("@container/sidebar").unwrap();
+        assert!(css.contains("container-name: sidebar;"));
     }
-    let mut out = String::with_capacity(body.len() + escaped_selector.len() + 64);
-    out.push_str("@container (min-width: ");
-    out.push_str(&size_expr);
-    out.push_str(") {\n  .");
-    out.push_str(escaped_selector);
-    out.push_str(" {\n");
-    out.push_str(&body);
-    out.push_str("  }\n}\n");
-    Some(out)
 }
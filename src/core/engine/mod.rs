@@ -1,10 +1,13 @@
+pub mod arbitrary;
 pub mod composite;
 pub mod container_queries;
 pub mod dynamic;
+pub mod order;
 pub mod screens;
 pub mod states;
 
-pub use composite::expand_composite;
+pub use arbitrary::{generate_arbitrary_css, split_class, split_variants, strip_important};
+pub use composite::{Composite, expand_composite};
 pub use dynamic::generate_dynamic_css;
 pub use screens::{build_block, sanitize_declarations, wrap_media_queries};
 pub use states::apply_wrappers_and_states;
@@ -18,7 +21,14 @@ use std::fs::File;
 use std::path::Path;
 use std::sync::Arc;
 
-use crate::core::color::{color::Argb, format_argb_as_oklch, theme::ThemeBuilder};
+use crate::config::{ThemeConfig, parse_theme_color};
+use crate::core::color::{
+    TONAL_PALETTES,
+    color::Argb,
+    format_argb_as_oklch, parse_tonal_step,
+    scheme::Scheme,
+    theme::{CustomColor, Palettes, Theme, ThemeBuilder},
+};
 
 #[allow(dead_code)]
 mod style_generated {
@@ -44,6 +54,134 @@ const DX_BASE_TOKENS: &[(&str, &str)] = &[("radius", "0.5rem")];
 
 const DEFAULT_THEME_SOURCE: u32 = 0xFF6750A4;
 
+/// Token written from a role of the generated light and dark schemes.
+type SchemeRole = (&'static str, fn(&Scheme) -> Argb, fn(&Scheme) -> Argb);
+
+/// Chart palette, shared by the light and dark schemes.
+fn chart_tokens(palettes: &Palettes) -> [(&'static str, Argb); 5] {
+    [
+        ("chart-1", palettes.primary.tone(60)),
+        ("chart-2", palettes.secondary.tone(60)),
+        ("chart-3", palettes.tertiary.tone(60)),
+        ("chart-4", palettes.primary.tone(80)),
+        ("chart-5", palettes.secondary.tone(80)),
+    ]
+}
+
+/// Roles that must not be mistaken for `destructive`, checked by `theme audit` with the chart
+/// colors and custom colors.
+const DISTINCT_ROLE_PAIRS: &[(&str, &str)] = &[
+    ("primary", "destructive"),
+    ("secondary", "destructive"),
+    ("accent", "destructive"),
+];
+
+const SCHEME_ROLES: &[SchemeRole] = &[
+    // Surface & content tokens
+    ("background", |s| s.background, |s| s.background),
+    ("foreground", |s| s.on_background, |s| s.on_background),
+    ("card", |s| s.surface, |s| s.surface),
+    ("card-foreground", |s| s.on_surface, |s| s.on_surface),
+    ("popover", |s| s.surface_bright, |s| s.surface_dim),
+    ("popover-foreground", |s| s.on_surface, |s| s.on_surface),
+    // Brand tokens
+    ("primary", |s| s.primary, |s| s.primary),
+    ("primary-foreground", |s| s.on_primary, |s| s.on_primary),
+    ("secondary", |s| s.secondary, |s| s.secondary),
+    (
+        "secondary-foreground",
+        |s| s.on_secondary,
+        |s| s.on_secondary,
+    ),
+    ("muted", |s| s.surface_variant, |s| s.surface_variant),
+    (
+        "muted-foreground",
+        |s| s.on_surface_variant,
+        |s| s.on_surface_variant,
+    ),
+    ("accent", |s| s.tertiary, |s| s.tertiary),
+    ("accent-foreground", |s| s.on_tertiary, |s| s.on_tertiary),
+    ("destructive", |s| s.error, |s| s.error),
+    ("destructive-foreground", |s| s.on_error, |s| s.on_error),
+    // Interaction tokens
+    ("border", |s| s.outline, |s| s.outline),
+    (
+        "input",
+        |s| s.surface_container_high,
+        |s| s.surface_container_high,
+    ),
+    ("ring", |s| s.surface_tint, |s| s.surface_tint),
+    // Sidebar tokens
+    (
+        "sidebar",
+        |s| s.surface_container_low,
+        |s| s.surface_container_low,
+    ),
+    ("sidebar-foreground", |s| s.on_surface, |s| s.on_surface),
+    ("sidebar-primary", |s| s.primary, |s| s.primary),
+    (
+        "sidebar-primary-foreground",
+        |s| s.on_primary,
+        |s| s.on_primary,
+    ),
+    (
+        "sidebar-accent",
+        |s| s.secondary_container,
+        |s| s.secondary_container,
+    ),
+    (
+        "sidebar-accent-foreground",
+        |s| s.on_secondary_container,
+        |s| s.on_secondary_container,
+    ),
+    (
+        "sidebar-border",
+        |s| s.outline_variant,
+        |s| s.outline_variant,
+    ),
+    ("sidebar-ring", |s| s.surface_tint, |s| s.surface_tint),
+    ("shadow-color", |s| s.shadow, |s| s.shadow),
+];
+
+/// True when the theme already generates `--<name>`: a scheme role, a chart color, or a tonal
+/// palette and its `<palette>-<n>` steps.
+pub fn is_theme_token(name: &str) -> bool {
+    SCHEME_ROLES.iter().any(|(token, ..)| *token == name)
+        || (1..=5).any(|i| name == format!("chart-{}", i))
+        || TONAL_PALETTES.contains(&name)
+        || parse_tonal_step(name).is_some()
+}
+
+/// Contrast levels emitted next to the default scheme, with their `data-contrast` value.
+const CONTRAST_LEVELS: &[(f64, &str)] = &[(0.5, "medium"), (1.0, "high")];
+
+/// Config slots written by build.rs that `style.fbs` does not declare; read as raw tables, so a
+/// style.bin or schema without them still works.
+const COLOR_UTILITIES_SLOT: flatbuffers::VOffsetT = 28;
+const COMPOSITES_SLOT: flatbuffers::VOffsetT = 30;
+
+/// Used when style.bin carries no `color_utilities.toml` data: (prefix, properties, child selector).
+const DEFAULT_COLOR_UTILITIES: &[(&str, &str, &str)] = &[
+    ("bg", "background-color", ""),
+    ("text", "color", ""),
+    ("border", "border-color", ""),
+    ("border-x", "border-left-color,border-right-color", ""),
+    ("border-y", "border-top-color,border-bottom-color", ""),
+    ("border-t", "border-top-color", ""),
+    ("border-r", "border-right-color", ""),
+    ("border-b", "border-bottom-color", ""),
+    ("border-l", "border-left-color", ""),
+    ("ring", "outline-color", ""),
+    ("fill", "fill", ""),
+    ("stroke", "stroke", ""),
+    ("outline", "outline-color", ""),
+    ("decoration", "text-decoration-color", ""),
+    ("caret", "caret-color", ""),
+    ("accent", "accent-color", ""),
+    ("shadow", "--shadow-color", ""),
+    ("divide", "border-color", ":not(:last-child)"),
+];
+
 #[derive(Clone)]
 pub struct GeneratorMeta {
     pub prefix: String,
@@ -59,11 +197,15 @@ pub struct StyleEngine {
     pub states: AHashMap<String, String>,
     pub container_queries: AHashMap<String, String>,
     pub colors: AHashMap<String, String>,
+    pub color_utilities: Vec<ColorUtility>,
+    pub composites: AHashMap<String, Composite>,
+    /// `[theme]` options from `.dx/config.toml`.
+    pub theme: ThemeConfig,
     pub generators: Option<Vec<GeneratorMeta>>,
     pub generator_map: Option<AHashMap<String, usize>>,
+    pub dynamic_properties: AHashMap<String, String>,
     #[allow(dead_code)]
     pub properties: Vec<PropertyMeta>,
-    #[allow(dead_code)]
     pub themes: Vec<ThemeDefinition>,
     pub theme_lookup: AHashMap<String, usize>,
     pub property_css: String,
@@ -71,6 +213,119 @@ pub struct StyleEngine {
     pub property_layer_raw: Option<String>,
 }
 
+/// A utility prefix that takes a color, e.g. `border-x` → `border-left-color, border-right-color`.
+#[derive(Clone, Debug)]
+pub struct ColorUtility {
+    pub prefix: String,
+    pub properties: Vec<String>,
+    pub selector: Option<String>,
+}
+
+impl ColorUtility {
+    fn new(prefix: &str, properties: &str, selector: &str) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            properties: properties
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            selector: (!selector.is_empty()).then(|| selector.to_string()),
+        }
+    }
+}
+
+fn default_color_utilities() -> Vec<ColorUtility> {
+    sort_color_utilities(
+        DEFAULT_COLOR_UTILITIES
+            .iter()
+            .map(|(prefix, properties, selector)| ColorUtility::new(prefix, properties, selector))
+            .collect(),
+    )
+}
+
+/// Longest prefix first so `border-x-red` matches `border-x` before `border`.
+fn sort_color_utilities(mut utilities: Vec<ColorUtility>) -> Vec<ColorUtility> {
+    utilities.sort_by(|a, b| {
+        b.prefix
+            .len()
+            .cmp(&a.prefix.len())
+            .then_with(|| a.prefix.cmp(&b.prefix))
+    });
+    utilities
+}
+
+fn read_color_utilities(config: &style_schema::Config<'_>) -> Option<Vec<ColorUtility>> {
+    use flatbuffers::{ForwardsUOffset, Table, Vector};
+    let tables = unsafe {
+        config
+            ._tab
+            .get::<ForwardsUOffset<Vector<'_, ForwardsUOffset<Table<'_>>>>>(
+                COLOR_UTILITIES_SLOT,
+                None,
+            )
+    }?;
+    let field = |table: &Table<'_>, slot| unsafe {
+        table
+            .get::<ForwardsUOffset<&str>>(slot, None)
+            .unwrap_or("")
+            .to_string()
+    };
+    let utilities: Vec<ColorUtility> = tables
+        .iter()
+        .map(|t| ColorUtility::new(&field(&t, 4), &field(&t, 6), &field(&t, 8)))
+        .filter(|u| !u.prefix.is_empty() && !u.properties.is_empty())
+        .collect();
+    (!utilities.is_empty()).then(|| sort_color_utilities(utilities))
+}
+
+/// Reads `composites.toml` entries: name, base tokens, then child/state/data/conditional rule
+/// lists of (key, tokens) and raw CSS rules.
+fn read_composites(config: &style_schema::Config<'_>) -> AHashMap<String, Composite> {
+    use flatbuffers::{ForwardsUOffset, Table, Vector};
+    type Tables<'a> = Vector<'a, ForwardsUOffset<Table<'a>>>;
+    let Some(tables) = (unsafe {
+        config
+            ._tab
+            .get::<ForwardsUOffset<Tables<'_>>>(COMPOSITES_SLOT, None)
+    }) else {
+        return AHashMap::new();
+    };
+    let field = |table: &Table<'_>, slot| unsafe {
+        table
+            .get::<ForwardsUOffset<&str>>(slot, None)
+            .unwrap_or("")
+            .to_string()
+    };
+    let rules = |table: &Table<'_>, slot| -> Vec<(String, String)> {
+        unsafe { table.get::<ForwardsUOffset<Tables<'_>>>(slot, None) }
+            .map(|list| list.iter().map(|r| (field(&r, 4), field(&r, 6))).collect())
+            .unwrap_or_default()
+    };
+    tables
+        .iter()
+        .filter_map(|t| {
+            let name = field(&t, 4);
+            if name.is_empty() {
+                return None;
+            }
+            let raw =
+                unsafe { t.get::<ForwardsUOffset<Vector<'_, ForwardsUOffset<&str>>>>(16, None) }
+                    .map(|list| list.iter().map(str::to_string).collect())
+                    .unwrap_or_default();
+            let composite = Composite::from_rules(
+                &field(&t, 6),
+                &rules(&t, 8),
+                &rules(&t, 10),
+                &rules(&t, 12),
+                &rules(&t, 14),
+                raw,
+            );
+            Some((name, composite))
+        })
+        .collect()
+}
+
 #[derive(Clone, Debug)]
 pub struct PropertyMeta {
     pub name: String,
@@ -85,6 +340,161 @@ pub struct ThemeDefinition {
     pub tokens: Vec<(String, String)>,
 }
 
+/// `[data-theme="acme.light"]`, the selector of a theme block and of the `theme-*:` variant.
+pub fn theme_attribute_selector(name: &str) -> String {
+    let mut out = String::from("[data-theme=");
+    let _ = cssparser::serialize_string(name, &mut out);
+    out.push(']');
+    out
+}
+
+/// Resolves color names to the values the `:root` and dark blocks give them, building the
+/// generated theme only when a name needs it.
+pub struct ThemeColors<'a> {
+    engine: &'a StyleEngine,
+    /// The `themes.toml` root theme and its dark counterpart, unless a generated palette replaces them.
+    root: Option<(&'a ThemeDefinition, Option<&'a ThemeDefinition>)>,
+    generated: std::cell::OnceCell<Theme>,
+    custom: std::cell::OnceCell<Vec<(String, Argb, Argb)>>,
+}
+
+impl ThemeColors<'_> {
+    /// The color a utility like `bg-<name>` paints in the light or dark scheme. `None` for names
+    /// that are not colors or whose value cannot be computed (`var()`, `currentColor`, keywords).
+    pub fn resolve(&self, name: &str, dark: bool) -> Option<Argb> {
+        use crate::core::color::parse_color_to_argb;
+        let engine = self.engine;
+        if let Some(value) = crate::core::color::derive_color_value(engine, name) {
+            return parse_color_to_argb(&value);
+        }
+        let generated = || {
+            self.generated
+                .get_or_init(|| engine.theme_builder().build())
+        };
+        match self.root {
+            Some((light, dark_theme)) => {
+                let theme = if dark {
+                    dark_theme.unwrap_or(light)
+                } else {
+                    light
+                };
+                if let Some((_, value)) = theme.tokens.iter().find(|(token, _)| token == name) {
+                    return parse_color_to_argb(value);
+                }
+            }
+            None => {
+                if let Some((_, light_role, dark_role)) =
+                    SCHEME_ROLES.iter().find(|(token, ..)| *token == name)
+                {
+                    let schemes = &generated().schemes;
+                    return Some(if dark {
+                        dark_role(&schemes.dark)
+                    } else {
+                        light_role(&schemes.light)
+                    });
+                }
+                if let Some((_, color)) = chart_tokens(&generated().palettes)
+                    .into_iter()
+                    .find(|(token, _)| *token == name)
+                {
+                    return Some(color);
+                }
+            }
+        }
+        let custom = self.custom.get_or_init(|| engine.custom_color_tokens());
+        if let Some((_, light, dark_color)) = custom.iter().find(|(token, ..)| token == name) {
+            return Some(if dark { *dark_color } else { *light });
+        }
+        let (palette, tone) = parse_tonal_step(name).filter(|_| self.root.is_none())?;
+        Some(generated().scheme_palettes.get(palette)?.tone(tone))
+    }
+
+    /// Names of the color tokens written to the `:root` and dark blocks.
+    pub fn token_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = match self.root {
+            Some((light, dark)) => {
+                let mut names: Vec<String> = Vec::new();
+                for (token, _) in light
+                    .tokens
+                    .iter()
+                    .chain(dark.iter().flat_map(|t| &t.tokens))
+                {
+                    if !names.contains(token) {
+                        names.push(token.clone());
+                    }
+                }
+                names
+            }
+            None => SCHEME_ROLES
+                .iter()
+                .map(|(token, ..)| token.to_string())
+                .chain((1..=5).map(|i| format!("chart-{}", i)))
+                .collect(),
+        };
+        let custom = self
+            .custom
+            .get_or_init(|| self.engine.custom_color_tokens());
+        names.extend(custom.iter().map(|(token, ..)| token.clone()));
+        names
+    }
+
+    /// Pairs `theme audit` expects to stay distinguishable: brand roles and custom colors against
+    /// `destructive`, custom colors against each other, and every two chart colors.
+    pub fn distinct_role_pairs(&self) -> Vec<(String, String)> {
+        let mut pairs: Vec<(String, String)> = DISTINCT_ROLE_PAIRS
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect();
+        let custom: Vec<&String> = self.engine.theme.custom_colors.keys().collect();
+        for (i, name) in custom.iter().enumerate() {
+            pairs.push((name.to_string(), "destructive".to_string()));
+            for other in &custom[i + 1..] {
+                pairs.push((name.to_string(), other.to_string()));
+            }
+        }
+        for a in 1..=5 {
+            for b in a + 1..=5 {
+                pairs.push((format!("chart-{}", a), format!("chart-{}", b)));
+            }
+        }
+        pairs
+    }
+}
+
+/// True for the line-based `BASE|`/`STATE|`/... encoding used by composites and animations.
+fn is_encoded_css(css: &str) -> bool {
+    [
+        "BASE|", "STATE|", "CHILD|", "COND|", "DATA|", "RAW|", "ANIM|",
+    ]
+    .iter()
+    .any(|p| css.contains(p))
+}
+
+/// A composite `raw` rule for one target selector. A single rule such as `& h1 { ... }` goes
+/// through `build_block`, so `!prose` marks its declarations important; anything with nested
+/// blocks is copied with `&` replaced.
+fn raw_rule(raw: &str, target: &str, important: bool) -> String {
+    let single = raw
+        .trim_end()
+        .strip_suffix('}')
+        .and_then(|rest| rest.split_once('{'))
+        .filter(|(_, declarations)| !declarations.contains(['{', '}']));
+    match single {
+        Some((selector, declarations)) => build_block(
+            &selector.trim().replace('&', target),
+            declarations,
+            important,
+        ),
+        None => {
+            let mut out = raw.replace('&', target);
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+            out
+        }
+    }
+}
+
 impl StyleEngine {
     pub fn load_from_disk() -> Result<Self, Box<dyn std::error::Error>> {
         let override_path = std::env::var("DX_STYLE_BIN").ok();
@@ -100,6 +510,7 @@ impl StyleEngine {
         let config = flatbuffers::root::<style_schema::Config>(&mmap)
             .map_err(|e| format!("Failed to parse style.bin: {}", e))?;
         let mut precompiled = AHashMap::new();
+        let mut dynamic_properties = AHashMap::new();
         if let Some(styles) = config.styles() {
             for style in styles {
                 let name = style.name();
@@ -115,6 +526,10 @@ impl StyleEngine {
         }
         if let Some(dynamics) = config.dynamics() {
             for dynamic in dynamics {
+                if !dynamic.key().is_empty() && !dynamic.property().is_empty() {
+                    dynamic_properties
+                        .insert(dynamic.key().to_string(), dynamic.property().to_string());
+                }
                 if let Some(values) = dynamic.values() {
                     for value in values {
                         let key = dynamic.key();
@@ -158,6 +573,8 @@ impl StyleEngine {
                 .map(|col| (col.name().to_string(), col.value().to_string()))
                 .collect()
         });
+        let color_utilities = read_color_utilities(&config).unwrap_or_else(default_color_utilities);
+        let composites = read_composites(&config);
         let generators: Option<Vec<GeneratorMeta>> = config.generators().map(|gen_list| {
             gen_list
                 .iter()
@@ -240,8 +657,12 @@ impl StyleEngine {
             states,
             container_queries,
             colors,
+            color_utilities,
+            composites,
+            theme: ThemeConfig::default(),
             generators,
             generator_map,
+            dynamic_properties,
             properties,
             themes,
             theme_lookup,
@@ -287,8 +708,12 @@ impl StyleEngine {
             states: AHashMap::new(),
             container_queries: AHashMap::new(),
             colors: AHashMap::new(),
+            color_utilities: default_color_utilities(),
+            composites: AHashMap::new(),
+            theme: ThemeConfig::default(),
             generators: None,
             generator_map: None,
+            dynamic_properties: AHashMap::new(),
             properties: Vec::new(),
             themes: Vec::new(),
             theme_lookup: AHashMap::new(),
@@ -308,6 +733,27 @@ impl StyleEngine {
             .and_then(|idx| self.themes.get(*idx))
     }
 
+    /// False when the `:root` colors come from a `themes.toml` theme: the generated palettes are
+    /// unrelated to its `--primary`, so tonal steps such as `bg-primary-40` are not offered.
+    pub fn has_tonal_palettes(&self) -> bool {
+        self.theme.source.is_some() || self.theme_by_name(self.theme.root_theme()).is_none()
+    }
+
+    pub fn has_theme_token(&self, name: &str) -> bool {
+        self.themes
+            .iter()
+            .any(|theme| theme.tokens.iter().any(|(token, _)| token == name))
+            || self.is_custom_color_token(name)
+    }
+
+    /// True for `success`, `on-success`, `success-container` and `on-success-container` when
+    /// `success` is listed under `[theme.custom_colors]`.
+    fn is_custom_color_token(&self, name: &str) -> bool {
+        let name = name.strip_prefix("on-").unwrap_or(name);
+        let name = name.strip_suffix("-container").unwrap_or(name);
+        self.theme.custom_colors.contains_key(name)
+    }
+
     pub fn compute_css(&self, class_name: &str) -> Option<String> {
         if class_name.starts_with("from(")
             || class_name.starts_with("to(")
@@ -315,33 +761,42 @@ impl StyleEngine {
         {
             return None;
         }
-        let mut last_colon = None;
-        for (i, b) in class_name.as_bytes().iter().enumerate() {
-            if *b == b':' {
-                last_colon = Some(i);
-            }
+        // The selector keeps the `!`; everything else resolves the class without it.
+        let (lookup, important) = strip_important(class_name);
+        let (prefix_segment, base_class) = split_class(&lookup);
+        // A misspelled variant would otherwise be dropped and the rule applied unconditionally.
+        if !lookup.starts_with("animate:")
+            && crate::core::engine::states::unknown_variant(self, prefix_segment).is_some()
+        {
+            return None;
         }
-        let (prefix_segment, base_class) = if let Some(idx) = last_colon {
-            (&class_name[..idx], &class_name[idx + 1..])
-        } else {
-            ("", class_name)
-        };
-        let (media_queries, pseudo_classes, wrappers) =
-            crate::core::engine::apply_wrappers_and_states(self, prefix_segment);
-        let core_css_raw = crate::core::engine::expand_composite(self, class_name)
+        let core_css_raw = crate::core::engine::expand_composite(self, &lookup)
             .or_else(|| self.precompiled.get(base_class).cloned())
+            .or_else(|| {
+                crate::core::engine::container_queries::generate_container_utility(base_class)
+            })
+            .or_else(|| crate::core::engine::generate_arbitrary_css(self, base_class))
             .or_else(|| crate::core::color::generate_color_css(self, base_class))
             .or_else(|| {
-                if class_name.contains(' ') {
+                if lookup.contains(' ') {
                     None
                 } else {
-                    crate::core::animation::generate_animation_css(class_name)
+                    crate::core::animation::generate_animation_css(&lookup)
                 }
             })
             .or_else(|| crate::core::engine::generate_dynamic_css(self, base_class))
             .or_else(|| crate::core::engine::expand_composite(self, base_class));
         core_css_raw.map(|mut css| {
             css = crate::core::engine::sanitize_declarations(&css);
+            // `::before`/`::after` only render with `content`; `content-[...]` (or a
+            // `--dx-content` inherited from the element) overrides the empty default.
+            if matches!(
+                crate::core::engine::states::pseudo_element(prefix_segment),
+                Some("::before" | "::after")
+            ) && !is_encoded_css(&css)
+            {
+                css = format!("content: var(--dx-content, ''); {}", css);
+            }
             let mut escaped_ident = String::with_capacity(class_name.len() + 8);
             struct Acc<'a> {
                 buf: &'a mut String,
@@ -373,13 +828,23 @@ impl StyleEngine {
                     }
                 }
             }
-            let mut selector =
-                String::with_capacity(escaped_ident.len() + pseudo_classes.len() + 2);
-            selector.push('.');
-            selector.push_str(&escaped_ident);
-            selector.push_str(&pseudo_classes);
-            let blocks = self.decode_encoded_css(&css, &selector, &wrappers);
-            crate::core::engine::wrap_media_queries(blocks, &media_queries)
+            let passes = crate::core::engine::states::color_scheme_passes(self, prefix_segment);
+            let mut out = String::new();
+            for scheme in 0..passes {
+                let (media_queries, pseudo_classes, wrappers) =
+                    crate::core::engine::apply_wrappers_and_states(self, prefix_segment, scheme);
+                let mut selector =
+                    String::with_capacity(escaped_ident.len() + pseudo_classes.len() + 2);
+                selector.push('.');
+                selector.push_str(&escaped_ident);
+                selector.push_str(&pseudo_classes);
+                let blocks = self.decode_encoded_css(&css, &selector, &wrappers, important);
+                out.push_str(&crate::core::engine::wrap_media_queries(
+                    blocks,
+                    &media_queries,
+                ));
+            }
+            out
         })
     }
 
@@ -387,6 +852,172 @@ impl StyleEngine {
         self.compute_css(class)
     }
 
+    /// Wraps the dark token declarations for the configured `dark_mode`: a `.dark` (or custom
+    /// selector) block, a `prefers-color-scheme` media block on `:root`, or both. `scope` narrows
+    /// the block to an attribute selector such as `[data-contrast="high"]`.
+    fn dark_vars_block(&self, declarations: &str, scope: Option<&str>) -> String {
+        let mut out = String::new();
+        if let Some(selector) = self.theme.dark_selector() {
+            out.push_str(&format!(
+                "{}{} {{\n{}}}\n",
+                selector,
+                scope.unwrap_or(""),
+                declarations
+            ));
+        }
+        if self.theme.follows_media() {
+            out.push_str(&wrap_media_queries(
+                format!("{} {{\n{}}}\n", scope.unwrap_or(":root"), declarations),
+                &["@media (prefers-color-scheme: dark)".to_string()],
+            ));
+        }
+        out
+    }
+
+    /// Medium- and high-contrast versions of the generated scheme roles. High contrast follows
+    /// `prefers-contrast: more`; both are also available through a `data-contrast` attribute on
+    /// the root element.
+    fn contrast_vars_blocks(&self, format_color: impl Fn(Argb) -> String) -> String {
+        use std::fmt::Write as _;
+        let mut out = String::new();
+        for (level, name) in CONTRAST_LEVELS {
+            let theme = self.theme_builder().contrast_level(*level).build();
+            let mut light = String::new();
+            let mut dark = String::new();
+            for (token, light_role, dark_role) in SCHEME_ROLES {
+                let light_color = format_color(light_role(&theme.schemes.light));
+                let _ = writeln!(light, "  --{}: {};", token, light_color);
+                let dark_color = format_color(dark_role(&theme.schemes.dark));
+                let _ = writeln!(dark, "  --{}: {};", token, dark_color);
+            }
+            if *name == "high" {
+                let mut block = format!(":root {{\n{}}}\n", light);
+                block.push_str(&self.dark_vars_block(&dark, None));
+                out.push_str(&wrap_media_queries(
+                    block,
+                    &["@media (prefers-contrast: more)".to_string()],
+                ));
+            }
+            let scope = format!("[data-contrast=\"{}\"]", name);
+            let _ = write!(out, "{} {{\n{}}}\n", scope, light);
+            out.push_str(&self.dark_vars_block(&dark, Some(&scope)));
+        }
+        out
+    }
+
+    /// Builder for the generated palette, configured from `[theme] source`, `variant`,
+    /// `contrast_level` and the role overrides. Colors were validated when the config loaded.
+    pub fn theme_builder(&self) -> ThemeBuilder {
+        let color =
+            |value: &Option<String>| value.as_deref().and_then(|v| parse_theme_color("", v).ok());
+        let source = color(&self.theme.source).unwrap_or(Argb::from_u32(DEFAULT_THEME_SOURCE));
+        let mut builder = ThemeBuilder::with_source(source);
+        if let Some(variant) = &self.theme.variant {
+            builder = builder.variant(variant.clone());
+        }
+        if let Some(level) = self.theme.contrast_level {
+            builder = builder.contrast_level(level.clamp(-1.0, 1.0));
+        }
+        if let Some(primary) = color(&self.theme.primary) {
+            builder = builder.primary(primary);
+        }
+        if let Some(secondary) = color(&self.theme.secondary) {
+            builder = builder.secondary(secondary);
+        }
+        if let Some(tertiary) = color(&self.theme.tertiary) {
+            builder = builder.tertiary(tertiary);
+        }
+        builder
+    }
+
+    /// Light and dark values of the four tokens of every `[theme.custom_colors]` entry, harmonized
+    /// with the theme source when the entry asks for it.
+    fn custom_color_tokens(&self) -> Vec<(String, Argb, Argb)> {
+        let colors: Vec<CustomColor> = self
+            .theme
+            .custom_colors
+            .iter()
+            .filter_map(|(name, color)| {
+                Some(CustomColor {
+                    value: parse_theme_color(name, &color.value).ok()?,
+                    name: name.clone(),
+                    blend: color.blend,
+                })
+            })
+            .collect();
+        if colors.is_empty() {
+            return Vec::new();
+        }
+        let theme = self.theme_builder().custom_colors(colors).build();
+        let mut tokens = Vec::new();
+        for group in theme.custom_colors {
+            let name = &group.color.name;
+            let (light, dark) = (&group.light, &group.dark);
+            tokens.push((name.clone(), light.color, dark.color));
+            tokens.push((format!("on-{}", name), light.on_color, dark.on_color));
+            tokens.push((
+                format!("{}-container", name),
+                light.color_container,
+                dark.color_container,
+            ));
+            tokens.push((
+                format!("on-{}-container", name),
+                light.on_color_container,
+                dark.on_color_container,
+            ));
+        }
+        tokens
+    }
+
+    /// Color lookup matching the variable blocks of `generate_color_vars_for`.
+    pub fn theme_colors(&self) -> ThemeColors<'_> {
+        let root = self
+            .theme_by_name(self.theme.root_theme())
+            .filter(|_| self.theme.source.is_none())
+            .map(|light| (light, self.theme_by_name(&self.theme.dark_theme())));
+        ThemeColors {
+            engine: self,
+            root,
+            generated: std::cell::OnceCell::new(),
+            custom: std::cell::OnceCell::new(),
+        }
+    }
+
+    /// `--primary-40` style variables for the tonal steps used by color utilities. A tone is the
+    /// same in light and dark; only the scheme roles move between tones.
+    fn tonal_step_tokens(
+        &self,
+        steps: &std::collections::BTreeSet<(&str, i32)>,
+    ) -> Vec<(String, Argb)> {
+        if steps.is_empty() {
+            return Vec::new();
+        }
+        let theme = self.theme_builder().build();
+        steps
+            .iter()
+            .filter_map(|(palette, tone)| {
+                let color = theme.scheme_palettes.get(palette)?.tone(*tone);
+                Some((format!("{}-{}", palette, tone), color))
+            })
+            .collect()
+    }
+
+    /// One `[data-theme="name"]` block per `themes.toml` entry, so any subtree can switch theme.
+    fn theme_attribute_blocks(&self) -> String {
+        use std::fmt::Write as _;
+        let mut out = String::new();
+        for theme in &self.themes {
+            let _ = writeln!(out, "{} {{", theme_attribute_selector(&theme.name));
+            for (name, value) in &theme.tokens {
+                let _ = writeln!(out, "  --{}: {};", name, value);
+            }
+            out.push_str("}\n");
+        }
+        out
+    }
+
+    /// Returns the `:root` block and, after it, the dark block(s) followed by one
+    /// `[data-theme]` block per theme.
     pub fn generate_color_vars_for<'a, I>(&self, classes: I) -> (String, String)
     where
         I: IntoIterator<Item = &'a String>,
@@ -394,20 +1025,26 @@ impl StyleEngine {
         use std::collections::BTreeSet;
         use std::fmt::Write as _;
 
-        let mut needed: BTreeSet<&str> = BTreeSet::new();
+        let mut needed: BTreeSet<String> = BTreeSet::new();
         for c in classes.into_iter() {
-            let base = c.rsplit(':').next().unwrap_or(c);
-            if let Some(name) = base.strip_prefix("bg-") {
-                needed.insert(name);
-            }
-            if let Some(name) = base.strip_prefix("text-") {
-                needed.insert(name);
+            let (lookup, _) = strip_important(c);
+            let base = split_class(&lookup).1;
+            if let Some(found) = crate::core::color::match_color_utility(self, base) {
+                needed.insert(found.name.to_string());
             }
         }
 
         let mut token_entries: Vec<(String, String)> = Vec::new();
+        let mut tonal_steps: BTreeSet<(&str, i32)> = BTreeSet::new();
 
         for name in &needed {
+            if let Some(step) = parse_tonal_step(name)
+                .filter(|_| crate::core::color::derive_color_value(self, name).is_none())
+                .filter(|_| !self.has_theme_token(name))
+            {
+                tonal_steps.insert(step);
+                continue;
+            }
             if let Some(mut val) = crate::core::color::derive_color_value(self, name) {
                 if let Some(oklch) = crate::core::color::normalize_color_to_oklch(&val) {
                     val = oklch;
@@ -438,28 +1075,49 @@ impl StyleEngine {
             out
         };
 
-        if let (Some(light_theme), Some(dark_theme)) = (
-            self.theme_by_name("dx.light"),
-            self.theme_by_name("dx.dark"),
-        ) {
+        let root_theme = self
+            .theme_by_name(self.theme.root_theme())
+            .filter(|_| self.theme.source.is_none());
+        if let Some(light_theme) = root_theme {
+            let dark_theme = self.theme_by_name(&self.theme.dark_theme());
             let mut root = String::from(":root {\n");
-            let mut dark = String::from(".dark {\n");
+            let mut dark = String::new();
 
             for (name, value) in &light_theme.tokens {
                 let _ = writeln!(root, "  --{}: {};", name, value);
             }
 
-            for (name, value) in &dark_theme.tokens {
+            for (name, value) in dark_theme.iter().flat_map(|t| &t.tokens) {
                 let _ = writeln!(dark, "  --{}: {};", name, value);
             }
 
+            let custom_tokens = self.custom_color_tokens();
+            for (name, light, dark_color) in &custom_tokens {
+                let _ = writeln!(root, "  --{}: {};", name, format_argb_as_oklch(*light));
+                let _ = writeln!(dark, "  --{}: {};", name, format_argb_as_oklch(*dark_color));
+            }
+
+            let has_dark = dark_theme.is_some() || !custom_tokens.is_empty();
+            for (name, value) in &token_entries {
+                let normalized = format_token_value(value);
+                let _ = writeln!(root, "  --color-{}: {};", name, normalized);
+                if has_dark {
+                    let _ = writeln!(dark, "  --color-{}: {};", name, normalized);
+                }
+            }
+
             root.push_str("}\n");
-            dark.push_str("}\n");
-            return (root, dark);
+            let mut rest = if has_dark {
+                self.dark_vars_block(&dark, None)
+            } else {
+                String::new()
+            };
+            rest.push_str(&self.theme_attribute_blocks());
+            return (root, rest);
         }
 
         let mut root = String::from(":root {\n");
-        let mut dark = String::from(".dark {\n");
+        let mut dark = String::new();
 
         for (name, value) in DX_FONT_TOKENS {
             let normalized = format_token_value(value);
@@ -469,7 +1127,7 @@ impl StyleEngine {
             let normalized = format_token_value(value);
             let _ = writeln!(root, "  --{}: {};", name, normalized);
         }
-        let theme = ThemeBuilder::with_source(Argb::from_u32(DEFAULT_THEME_SOURCE)).build();
+        let theme = self.theme_builder().build();
         let light = &theme.schemes.light;
         let dark_scheme = &theme.schemes.dark;
 
@@ -484,104 +1142,27 @@ impl StyleEngine {
             let _ = writeln!(buffer, "  --{}: {};", name, normalized);
         };
 
-        // Surface & content tokens
-        write_argb_token(&mut root, "background", light.background);
-        write_argb_token(&mut dark, "background", dark_scheme.background);
-        write_argb_token(&mut root, "foreground", light.on_background);
-        write_argb_token(&mut dark, "foreground", dark_scheme.on_background);
-        write_argb_token(&mut root, "card", light.surface);
-        write_argb_token(&mut dark, "card", dark_scheme.surface);
-        write_argb_token(&mut root, "card-foreground", light.on_surface);
-        write_argb_token(&mut dark, "card-foreground", dark_scheme.on_surface);
-        write_argb_token(&mut root, "popover", light.surface_bright);
-        write_argb_token(&mut dark, "popover", dark_scheme.surface_dim);
-        write_argb_token(&mut root, "popover-foreground", light.on_surface);
-        write_argb_token(&mut dark, "popover-foreground", dark_scheme.on_surface);
-
-        // Brand tokens
-        write_argb_token(&mut root, "primary", light.primary);
-        write_argb_token(&mut dark, "primary", dark_scheme.primary);
-        write_argb_token(&mut root, "primary-foreground", light.on_primary);
-        write_argb_token(&mut dark, "primary-foreground", dark_scheme.on_primary);
-        write_argb_token(&mut root, "secondary", light.secondary);
-        write_argb_token(&mut dark, "secondary", dark_scheme.secondary);
-        write_argb_token(&mut root, "secondary-foreground", light.on_secondary);
-        write_argb_token(&mut dark, "secondary-foreground", dark_scheme.on_secondary);
-        write_argb_token(&mut root, "muted", light.surface_variant);
-        write_argb_token(&mut dark, "muted", dark_scheme.surface_variant);
-        write_argb_token(&mut root, "muted-foreground", light.on_surface_variant);
-        write_argb_token(
-            &mut dark,
-            "muted-foreground",
-            dark_scheme.on_surface_variant,
-        );
-        write_argb_token(&mut root, "accent", light.tertiary);
-        write_argb_token(&mut dark, "accent", dark_scheme.tertiary);
-        write_argb_token(&mut root, "accent-foreground", light.on_tertiary);
-        write_argb_token(&mut dark, "accent-foreground", dark_scheme.on_tertiary);
-        write_argb_token(&mut root, "destructive", light.error);
-        write_argb_token(&mut dark, "destructive", dark_scheme.error);
-        write_argb_token(&mut root, "destructive-foreground", light.on_error);
-        write_argb_token(&mut dark, "destructive-foreground", dark_scheme.on_error);
-
-        // Interaction tokens
-        write_argb_token(&mut root, "border", light.outline);
-        write_argb_token(&mut dark, "border", dark_scheme.outline);
-        write_argb_token(&mut root, "input", light.surface_container_high);
-        write_argb_token(&mut dark, "input", dark_scheme.surface_container_high);
-        write_argb_token(&mut root, "ring", light.surface_tint);
-        write_argb_token(&mut dark, "ring", dark_scheme.surface_tint);
-
-        // Chart palette (shared across themes)
-        let chart_1 = theme.palettes.primary.tone(60);
-        let chart_2 = theme.palettes.secondary.tone(60);
-        let chart_3 = theme.palettes.tertiary.tone(60);
-        let chart_4 = theme.palettes.primary.tone(80);
-        let chart_5 = theme.palettes.secondary.tone(80);
-        for (name, color) in [
-            ("chart-1", chart_1),
-            ("chart-2", chart_2),
-            ("chart-3", chart_3),
-            ("chart-4", chart_4),
-            ("chart-5", chart_5),
-        ] {
+        for (name, light_role, dark_role) in SCHEME_ROLES {
+            write_argb_token(&mut root, name, light_role(light));
+            write_argb_token(&mut dark, name, dark_role(dark_scheme));
+        }
+
+        for (name, color) in chart_tokens(&theme.palettes) {
             write_argb_token(&mut root, name, color);
             write_argb_token(&mut dark, name, color);
         }
 
-        // Sidebar tokens
-        write_argb_token(&mut root, "sidebar", light.surface_container_low);
-        write_argb_token(&mut dark, "sidebar", dark_scheme.surface_container_low);
-        write_argb_token(&mut root, "sidebar-foreground", light.on_surface);
-        write_argb_token(&mut dark, "sidebar-foreground", dark_scheme.on_surface);
-        write_argb_token(&mut root, "sidebar-primary", light.primary);
-        write_argb_token(&mut dark, "sidebar-primary", dark_scheme.primary);
-        write_argb_token(&mut root, "sidebar-primary-foreground", light.on_primary);
-        write_argb_token(
-            &mut dark,
-            "sidebar-primary-foreground",
-            dark_scheme.on_primary,
-        );
-        write_argb_token(&mut root, "sidebar-accent", light.secondary_container);
-        write_argb_token(&mut dark, "sidebar-accent", dark_scheme.secondary_container);
-        write_argb_token(
-            &mut root,
-            "sidebar-accent-foreground",
-            light.on_secondary_container,
-        );
-        write_argb_token(
-            &mut dark,
-            "sidebar-accent-foreground",
-            dark_scheme.on_secondary_container,
-        );
-        write_argb_token(&mut root, "sidebar-border", light.outline_variant);
-        write_argb_token(&mut dark, "sidebar-border", dark_scheme.outline_variant);
-        write_argb_token(&mut root, "sidebar-ring", light.surface_tint);
-        write_argb_token(&mut dark, "sidebar-ring", dark_scheme.surface_tint);
+        for (name, light_color, dark_color) in self.custom_color_tokens() {
+            write_argb_token(&mut root, &name, light_color);
+            write_argb_token(&mut dark, &name, dark_color);
+        }
+
+        for (name, color) in self.tonal_step_tokens(&tonal_steps) {
+            write_argb_token(&mut root, &name, color);
+            write_argb_token(&mut dark, &name, color);
+        }
 
         // Shadows
-        write_argb_token(&mut root, "shadow-color", light.shadow);
-        write_argb_token(&mut dark, "shadow-color", dark_scheme.shadow);
         for target in [&mut root, &mut dark] {
             write_raw_token(target, "shadow-opacity", "0.18");
             write_raw_token(target, "shadow-blur", "2px");
@@ -597,25 +1178,30 @@ impl StyleEngine {
         }
 
         root.push_str("}\n");
-        dark.push_str("}\n");
-        (root, dark)
+        let mut rest = self.dark_vars_block(&dark, None);
+        rest.push_str(
+            &self.contrast_vars_blocks(|color| format_token_value(&format_argb_as_oklch(color))),
+        );
+        rest.push_str(&self.theme_attribute_blocks());
+        (root, rest)
     }
 
-    fn decode_encoded_css(&self, css: &str, selector: &str, wrappers: &[String]) -> String {
+    fn decode_encoded_css(
+        &self,
+        css: &str,
+        selector: &str,
+        wrappers: &[String],
+        important: bool,
+    ) -> String {
         use crate::core::engine::build_block;
-        let is_encoded = [
-            "BASE|", "STATE|", "CHILD|", "COND|", "DATA|", "RAW|", "ANIM|",
-        ]
-        .iter()
-        .any(|p| css.contains(p));
-        if !is_encoded {
+        if !is_encoded_css(css) {
             if wrappers.is_empty() {
-                return build_block(selector, css);
+                return build_block(selector, css, important);
             }
             let mut out = String::new();
             for w in wrappers {
                 let sel = w.replace('&', selector);
-                out.push_str(&build_block(&sel, css));
+                out.push_str(&build_block(&sel, css, important));
                 out.push('\n');
             }
             if out.ends_with('\n') {
@@ -625,6 +1211,12 @@ impl StyleEngine {
         }
         let mut out = String::new();
         let mut pending_anim: Option<crate::core::animation::PendingAnimation> = None;
+        // State, child and data rules follow the class's own variants (`md:hover:btn`).
+        let targets: Vec<String> = if wrappers.is_empty() {
+            vec![selector.to_string()]
+        } else {
+            wrappers.iter().map(|w| w.replace('&', selector)).collect()
+        };
         let lines: Vec<&str> = if css.contains('\n') {
             css.lines().collect()
         } else {
@@ -636,11 +1228,11 @@ impl StyleEngine {
             }
             if let Some(rest) = line.strip_prefix("BASE|") {
                 if wrappers.is_empty() {
-                    out.push_str(&build_block(selector, rest));
+                    out.push_str(&build_block(selector, rest, important));
                 } else {
                     for w in wrappers {
                         let sel = w.replace('&', selector);
-                        out.push_str(&build_block(&sel, rest));
+                        out.push_str(&build_block(&sel, rest, important));
                         out.push('\n');
                     }
                     if out.ends_with('\n') {
@@ -652,35 +1244,65 @@ impl StyleEngine {
                 let mut parts = rest.splitn(2, '|');
                 let state = parts.next().unwrap_or("");
                 let decls = parts.next().unwrap_or("");
-                if state == "dark" {
-                    out.push_str(&build_block(&format!(".dark {}", selector), decls));
-                } else if state == "light" {
-                    out.push_str(&build_block(&format!(":root {}", selector), decls));
+                for target in &targets {
+                    if state == "dark" || state == "light" {
+                        let conditions = crate::core::engine::states::color_scheme_conditions(
+                            &self.theme,
+                            state == "dark",
+                        );
+                        for condition in conditions {
+                            let sel = condition
+                                .wrapper
+                                .map_or_else(|| target.clone(), |w| w.replace('&', target));
+                            let block = build_block(&sel, decls, important);
+                            match condition.media {
+                                Some(feature) => out.push_str(&wrap_media_queries(
+                                    block,
+                                    &[format!("@media {}", feature)],
+                                )),
+                                None => out.push_str(&block),
+                            }
+                        }
+                    } else {
+                        out.push_str(&build_block(
+                            &format!("{}:{}", target, state),
+                            decls,
+                            important,
+                        ));
+                    }
                     out.push('\n');
-                    out.push_str(&build_block(&format!(".light {}", selector), decls));
-                } else {
-                    out.push_str(&build_block(&format!("{}:{}", selector, state), decls));
                 }
-                out.push('\n');
             } else if let Some(rest) = line.strip_prefix("CHILD|") {
                 let mut parts = rest.splitn(2, '|');
                 let child = parts.next().unwrap_or("");
                 let decls = parts.next().unwrap_or("");
-                out.push_str(&build_block(&format!("{} > {}", selector, child), decls));
-                out.push('\n');
+                for target in &targets {
+                    out.push_str(&build_block(
+                        &format!("{} > {}", target, child),
+                        decls,
+                        important,
+                    ));
+                    out.push('\n');
+                }
             } else if let Some(rest) = line.strip_prefix("DATA|") {
                 let mut parts = rest.splitn(2, '|');
                 let data = parts.next().unwrap_or("");
                 let decls = parts.next().unwrap_or("");
-                out.push_str(&build_block(&format!("{}[data-{}]", selector, data), decls));
-                out.push('\n');
+                for target in &targets {
+                    out.push_str(&build_block(
+                        &format!("{}[data-{}]", target, data),
+                        decls,
+                        important,
+                    ));
+                    out.push('\n');
+                }
             } else if let Some(rest) = line.strip_prefix("COND|") {
                 let mut parts = rest.splitn(2, '|');
                 let cond = parts.next().unwrap_or("");
                 let decls = parts.next().unwrap_or("");
                 if let Some(val) = cond.strip_prefix("@container>") {
                     out.push_str(&format!("@container (min-width: {}) {{\n", val));
-                    for l in build_block(selector, decls).lines() {
+                    for l in build_block(selector, decls, important).lines() {
                         out.push_str("  ");
                         out.push_str(l);
                         out.push('\n');
@@ -689,7 +1311,7 @@ impl StyleEngine {
                 } else if let Some(bp) = cond.strip_prefix("screen:") {
                     if let Some(v) = self.screens.get(bp) {
                         out.push_str(&format!("@media (min-width: {}) {{\n", v));
-                        for l in build_block(selector, decls).lines() {
+                        for l in build_block(selector, decls, important).lines() {
                             out.push_str("  ");
                             out.push_str(l);
                             out.push('\n');
@@ -700,9 +1322,8 @@ impl StyleEngine {
             } else if line.starts_with("ANIM|") {
                 crate::core::animation::process_anim_line(line, &mut pending_anim);
             } else if let Some(raw) = line.strip_prefix("RAW|") {
-                out.push_str(raw);
-                if !raw.ends_with('\n') {
-                    out.push('\n');
+                for target in &targets {
+                    out.push_str(&raw_rule(raw, target, important));
                 }
             }
         }
@@ -711,6 +1332,7 @@ impl StyleEngine {
             selector,
             &mut pending_anim,
             &mut out,
+            important,
         );
         if out.ends_with('\n') {
             out.pop();
@@ -718,3 +1340,210 @@ impl StyleEngine {
         out
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn theme(name: &str, primary: &str) -> ThemeDefinition {
+        ThemeDefinition {
+            name: name.to_string(),
+            tokens: vec![("primary".to_string(), primary.to_string())],
+        }
+    }
+
+    #[test]
+    fn every_theme_gets_a_data_theme_block() {
+        let mut engine = StyleEngine::empty();
+        engine.themes = vec![
+            theme("dx.light", "white"),
+            theme("dx.dark", "black"),
+            theme("acme.light", "red"),
+            theme("acme.dark", "maroon"),
+        ];
+        engine.theme_lookup = engine
+            .themes
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t.name.clone(), i))
+            .collect();
+        let (root, rest) = engine.generate_color_vars_for(std::iter::empty());
+        assert_eq!(root, ":root {\n  --primary: white;\n}\n");
+        assert!(rest.starts_with(".dark {\n  --primary: black;\n}\n"));
+        assert!(rest.contains("[data-theme=\"acme.light\"] {\n  --primary: red;\n}\n"));
+        assert!(rest.contains("[data-theme=\"dx.dark\"] {\n  --primary: black;\n}\n"));
+
+        engine.theme.root_theme = Some("acme.light".to_string());
+        let (root, rest) = engine.generate_color_vars_for(std::iter::empty());
+        assert_eq!(root, ":root {\n  --primary: red;\n}\n");
+        assert!(rest.starts_with(".dark {\n  --primary: maroon;\n}\n"));
+        assert!(!rest.contains("data-contrast"));
+    }
+
+    #[test]
+    fn generated_palette_has_contrast_sets() {
+        let engine = StyleEngine::empty();
+        let (root, rest) = engine.generate_color_vars_for(std::iter::empty());
+        let token = |block: &str, name: &str| {
+            block
+                .lines()
+                .find(|l| l.trim_start().starts_with(&format!("--{}:", name)))
+                .map(|l| l.trim().to_string())
+        };
+        let high_media = rest
+            .split("@media (prefers-contrast: more) {\n  :root {\n")
+            .nth(1)
+            .unwrap();
+        let high_attr = rest.split("[data-contrast=\"high\"] {\n").nth(1).unwrap();
+        assert_eq!(token(high_media, "primary"), token(high_attr, "primary"));
+        assert_ne!(token(high_attr, "primary"), token(&root, "primary"));
+        assert!(rest.contains("[data-contrast=\"medium\"] {\n"));
+        assert!(rest.contains(".dark[data-contrast=\"high\"] {\n"));
+    }
+
+    #[test]
+    fn custom_colors_add_tokens_and_resolve_as_utilities() {
+        use crate::config::CustomColorConfig;
+        let mut engine = StyleEngine::empty();
+        engine.theme.custom_colors.insert(
+            "success".to_string(),
+            CustomColorConfig {
+                value: "#16a34a".to_string(),
+                blend: true,
+            },
+        );
+        let (root, rest) = engine.generate_color_vars_for(std::iter::empty());
+        let dark = rest.split(".dark {\n").nth(1).unwrap();
+        for name in [
+            "success",
+            "on-success",
+            "success-container",
+            "on-success-container",
+        ] {
+            let decl = format!("  --{}: oklch(", name);
+            assert!(root.contains(&decl), "{} missing from :root", name);
+            assert!(dark.contains(&decl), "{} missing from .dark", name);
+        }
+        assert_eq!(
+            crate::core::color::generate_color_css(&engine, "bg-success").as_deref(),
+            Some("background-color: var(--success)")
+        );
+        assert_eq!(
+            crate::core::color::generate_color_css(&engine, "text-on-success-container").as_deref(),
+            Some("color: var(--on-success-container)")
+        );
+        assert!(crate::core::color::generate_color_css(&engine, "bg-warning").is_none());
+    }
+
+    #[test]
+    fn only_used_tonal_steps_are_emitted() {
+        let engine = StyleEngine::empty();
+        let classes: Vec<String> = ["bg-primary-40", "dark:border-tertiary-[73]", "p-4"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let (root, rest) = engine.generate_color_vars_for(&classes);
+        let token = |block: &str, name: &str| {
+            block
+                .lines()
+                .find_map(|l| l.trim().strip_prefix(&format!("--{}:", name)))
+                .map(str::to_string)
+        };
+        assert_eq!(token(&root, "primary-40"), token(&root, "primary"));
+        assert!(token(&root, "tertiary-73").is_some());
+        assert!(token(&rest, "tertiary-73").is_some());
+        assert!(token(&root, "primary-90").is_none());
+    }
+
+    #[test]
+    fn chart_colors_keep_the_core_palette() {
+        let engine = StyleEngine::empty();
+        let (root, _) = engine.generate_color_vars_for(&["bg-primary-60".to_string()]);
+        let token = |name: &str| {
+            root.lines()
+                .find_map(|l| l.trim().strip_prefix(&format!("--{}: ", name)))
+                .map(str::to_string)
+        };
+        // The default theme's chart colors from before tonal steps existed.
+        for (name, value) in [
+            ("chart-1", "oklch(0.67 0.129 294.69);"),
+            ("chart-2", "oklch(0.66 0.035 301.29);"),
+            ("chart-3", "oklch(0.66 0.065 359.35);"),
+            ("chart-4", "oklch(0.83 0.093 297.54);"),
+            ("chart-5", "oklch(0.83 0.036 301.98);"),
+        ] {
+            assert_eq!(token(name).as_deref(), Some(value), "{}", name);
+        }
+        // Tonal steps come from the scheme's own palettes instead.
+        assert_eq!(
+            token("primary-60").as_deref(),
+            Some("oklch(0.66 0.092 296.38);")
+        );
+    }
+
+    #[test]
+    fn tonal_steps_need_the_generated_palette() {
+        let mut engine = StyleEngine::empty();
+        engine.themes = vec![theme("dx.light", "white"), theme("dx.dark", "black")];
+        engine.theme_lookup = engine
+            .themes
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t.name.clone(), i))
+            .collect();
+        assert!(engine.css_for_class("bg-primary-40").is_none());
+        let (root, rest) = engine.generate_color_vars_for(&["bg-primary-40".to_string()]);
+        assert!(!root.contains("--primary-40") && !rest.contains("--primary-40"));
+        assert!(engine.theme_colors().resolve("primary-40", false).is_none());
+
+        engine.theme.source = Some("#0ea5e9".to_string());
+        assert!(engine.css_for_class("bg-primary-40").is_some());
+    }
+
+    #[test]
+    fn theme_colors_list_and_pair_the_generated_tokens() {
+        use crate::config::CustomColorConfig;
+        let mut engine = StyleEngine::empty();
+        engine.theme.custom_colors.insert(
+            "success".to_string(),
+            CustomColorConfig {
+                value: "#16a34a".to_string(),
+                blend: false,
+            },
+        );
+        let colors = engine.theme_colors();
+        let names = colors.token_names();
+        for name in ["primary", "destructive", "chart-5", "on-success-container"] {
+            assert!(names.iter().any(|n| n == name), "{} missing", name);
+            assert!(colors.resolve(name, true).is_some(), "{} unresolved", name);
+        }
+        let pairs = colors.distinct_role_pairs();
+        assert!(pairs.contains(&("success".to_string(), "destructive".to_string())));
+        assert!(pairs.contains(&("chart-1".to_string(), "chart-5".to_string())));
+        assert_eq!(pairs.len(), 3 + 1 + 10);
+    }
+
+    #[test]
+    fn theme_options_drive_the_generated_palette() {
+        let mut engine = StyleEngine::empty();
+        let primary = |engine: &StyleEngine| {
+            let (root, _) = engine.generate_color_vars_for(std::iter::empty());
+            root.lines()
+                .find(|l| l.trim_start().starts_with("--primary:"))
+                .unwrap()
+                .to_string()
+        };
+        let default = primary(&engine);
+        engine.theme.source = Some("#0ea5e9".to_string());
+        let sky = primary(&engine);
+        assert_ne!(sky, default);
+        engine.theme.variant = Some(crate::core::color::dynamic_color::Variant::Monochrome);
+        assert_ne!(primary(&engine), sky);
+        engine.theme.variant = None;
+        engine.theme.contrast_level = Some(1.0);
+        assert_ne!(primary(&engine), sky);
+        engine.theme.contrast_level = None;
+        engine.theme.primary = Some("#16a34a".to_string());
+        assert_ne!(primary(&engine), sky);
+    }
+}
@@ -0,0 +1,458 @@
+//! Cascade order of the utilities layer.
+//!
+//! Every rule in `@layer utilities` has the same specificity, so source order decides which one
+//! wins. Classes are sorted so that later rules are the ones meant to override earlier ones:
+//! base utilities first, then state variants, then `max-*` ranges from the widest to the
+//! narrowest, then breakpoints from the narrowest `screens.toml` width to the widest, then other
+//! media variants (`print:`, `motion-reduce:`, ...). Within each tier, rules follow the property
+//! groups below with shorthands before their longhands, so `p-4 px-2` always leaves `px-2` in
+//! charge of the inline padding.
+
+use ahash::AHashMap;
+
+use super::container_queries::container_variant;
+use super::screens::{MediaVariant, media_variant};
+use super::states::relational_variant;
+use super::{StyleEngine, split_class, split_variants, strip_important};
+
+/// Property families in cascade order, matched on the longest prefix.
+const PROPERTY_GROUPS: &[&[&str]] = &[
+    &[
+        "container",
+        "pointer-events",
+        "visibility",
+        "position",
+        "inset",
+        "top",
+        "right",
+        "bottom",
+        "left",
+        "isolation",
+        "z-index",
+        "float",
+        "clear",
+        "box-sizing",
+        "display",
+        "table-layout",
+        "overflow",
+        "overscroll-behavior",
+        "object",
+    ],
+    &[
+        "order",
+        "grid",
+        "flex",
+        "justify",
+        "align",
+        "place",
+        "gap",
+        "row-gap",
+        "column-gap",
+        "columns",
+    ],
+    &["margin", "padding", "scroll-margin", "scroll-padding"],
+    &[
+        "aspect-ratio",
+        "width",
+        "min-width",
+        "max-width",
+        "height",
+        "min-height",
+        "max-height",
+        "inline-size",
+        "block-size",
+    ],
+    &[
+        "font",
+        "text",
+        "line-height",
+        "letter-spacing",
+        "white-space",
+        "word",
+        "overflow-wrap",
+        "list-style",
+        "vertical-align",
+        "color",
+        "hyphens",
+        "tab-size",
+        "content",
+    ],
+    &["background", "mask"],
+    &["border", "outline"],
+    &[
+        "box-shadow",
+        "opacity",
+        "mix-blend-mode",
+        "filter",
+        "backdrop-filter",
+    ],
+    &["transform", "translate", "rotate", "scale"],
+    &["transition", "animation", "will-change"],
+    &[
+        "cursor",
+        "user-select",
+        "resize",
+        "scroll",
+        "touch-action",
+        "appearance",
+        "caret-color",
+        "accent-color",
+        "fill",
+        "stroke",
+    ],
+];
+
+/// Pseudo-class variants in the order their rules should appear (link states keep the usual
+/// visited, hover, focus, active sequence). Variants not listed here sort after these by name.
+const STATE_ORDER: &[&str] = &[
+    "first",
+    "last",
+    "only",
+    "odd",
+    "even",
+    "first-of-type",
+    "last-of-type",
+    "empty",
+    "open",
+    "default",
+    "checked",
+    "indeterminate",
+    "placeholder-shown",
+    "autofill",
+    "optional",
+    "required",
+    "valid",
+    "invalid",
+    "in-range",
+    "out-of-range",
+    "read-only",
+    "visited",
+    "target",
+    "focus-within",
+    "hover",
+    "focus",
+    "focus-visible",
+    "active",
+    "enabled",
+    "disabled",
+    "dark",
+    "light",
+];
+
+/// Sort key of one class in the utilities layer. The derived `Ord` compares fields top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CascadeKey {
+    /// Media tier of the variant chain; see `media_rank`.
+    screen: Option<(u8, u32)>,
+    /// Container-query tier, ranked like `screen`.
+    container: Option<(u8, u32)>,
+    /// Latest state variant in `STATE_ORDER`; `None` for classes without state variants.
+    state: Option<usize>,
+    variants: usize,
+    group: usize,
+    /// Position of the matched family inside its group.
+    family: usize,
+    /// Number of sub-properties named by the declared property; shorthands have the fewest.
+    depth: usize,
+    class: String,
+}
+
+/// Computes the key for `class`. The property is taken from the first declaration the engine
+/// generates for the class without its variants.
+pub fn cascade_key(engine: &StyleEngine, class: &str) -> CascadeKey {
+    let (lookup, _) = strip_important(class);
+    let (prefix, base) = split_class(&lookup);
+    let mut key = CascadeKey {
+        screen: None,
+        container: None,
+        state: None,
+        variants: 0,
+        group: PROPERTY_GROUPS.len() + 1,
+        family: 0,
+        depth: 0,
+        class: class.to_string(),
+    };
+    if !prefix.is_empty() {
+        let mut media: Vec<MediaVariant> = Vec::new();
+        let mut containers: Vec<MediaVariant> = Vec::new();
+        for part in split_variants(prefix) {
+            key.variants += 1;
+            if let Some(variant) = media_variant(&engine.screens, part) {
+                media.push(variant);
+            } else if let Some(variant) = container_variant(engine, part) {
+                containers.push(variant.bound);
+            } else {
+                let state = relational_variant(part).map_or(part, |(_, state, _)| state);
+                let rank = STATE_ORDER
+                    .iter()
+                    .position(|s| *s == state)
+                    .unwrap_or(STATE_ORDER.len());
+                key.state = key.state.max(Some(rank));
+            }
+        }
+        key.screen = media_rank(&media);
+        key.container = media_rank(&containers);
+    }
+    if let Some(property) = engine
+        .css_for_class(base)
+        .as_deref()
+        .and_then(first_property)
+    {
+        (key.group, key.family, key.depth) = property_rank(property);
+    }
+    key
+}
+
+/// Keys of the classes seen by earlier rebuilds. Computing a key generates the class's CSS, and
+/// the engine does not change while the process runs, so each class is only keyed once.
+#[derive(Debug, Default)]
+pub struct KeyCache {
+    keys: AHashMap<String, CascadeKey>,
+}
+
+impl KeyCache {
+    pub fn key(&mut self, engine: &StyleEngine, class: &str) -> CascadeKey {
+        if let Some(key) = self.keys.get(class) {
+            return key.clone();
+        }
+        let key = cascade_key(engine, class);
+        self.keys.insert(class.to_string(), key.clone());
+        key
+    }
+}
+
+/// Sorts the full class set into cascade order. Afterwards `cache` only holds keys for `classes`.
+pub fn sort_classes(engine: &StyleEngine, classes: &mut [String], cache: &mut KeyCache) {
+    let mut keyed: Vec<(CascadeKey, String)> = classes
+        .iter()
+        .map(|c| (cache.key(engine, c), c.clone()))
+        .collect();
+    keyed.sort_by(|a, b| a.0.cmp(&b.0));
+    cache.keys = keyed
+        .iter()
+        .map(|(key, class)| (class.clone(), key.clone()))
+        .collect();
+    for (slot, (_, class)) in classes.iter_mut().zip(keyed) {
+        *slot = class;
+    }
+}
+
+/// Group, family and shorthand depth of a CSS property. Custom properties sort after every group.
+fn property_rank(property: &str) -> (usize, usize, usize) {
+    if property.starts_with("--") {
+        return (PROPERTY_GROUPS.len() + 1, 0, 0);
+    }
+    let mut best: Option<(usize, usize, usize)> = None;
+    for (group, names) in PROPERTY_GROUPS.iter().enumerate() {
+        for (family, name) in names.iter().enumerate() {
+            let matches = property == *name
+                || property
+                    .strip_prefix(name)
+                    .is_some_and(|rest| rest.starts_with('-'));
+            if matches && best.is_none_or(|(_, _, len)| name.len() > len) {
+                best = Some((group, family, name.len()));
+            }
+        }
+    }
+    let (group, family) = best.map_or((PROPERTY_GROUPS.len(), 0), |(g, f, _)| (g, f));
+    (group, family, property.split('-').count())
+}
+
+/// Property name of the first declaration in a generated rule.
+fn first_property(css: &str) -> Option<&str> {
+    css.lines()
+        .map(str::trim)
+        .filter(|line| line.ends_with(';') && !line.starts_with('@'))
+        .find_map(|line| line.split_once(':').map(|(name, _)| name.trim()))
+}
+
+/// Orders media variant chains: `max-*` alone (widest bound first, so narrower ranges win), then
+/// chains with a min-width (narrowest first, ranges like `md:max-xl:` sorting by their lower bound),
+/// then media features and types.
+fn media_rank(media: &[MediaVariant]) -> Option<(u8, u32)> {
+    if media.is_empty() {
+        return None;
+    }
+    let widest_min = media
+        .iter()
+        .filter(|v| matches!(v, MediaVariant::Min(_)))
+        .map(|v| hundredths(v.width_px()))
+        .max();
+    let narrowest_max = media
+        .iter()
+        .filter(|v| matches!(v, MediaVariant::Max(_)))
+        .map(|v| hundredths(v.width_px()))
+        .min();
+    Some(match (widest_min, narrowest_max) {
+        (Some(min), _) => (1, min),
+        (None, Some(max)) => (0, u32::MAX - max),
+        (None, None) => (2, 0),
+    })
+}
+
+/// Pixels in hundredths; widths that do not convert sort after every known width.
+fn hundredths(px: Option<f64>) -> u32 {
+    match px {
+        Some(px) => ((px * 100.0).round() as u64).min(u32::MAX as u64 - 1) as u32,
+        None => u32::MAX,
+    }
+}
+
+impl CascadeKey {
+    /// True when a rule with this key may be appended after a rule with `tail` without breaking
+    /// cascade order.
+    pub fn follows(&self, tail: &CascadeKey) -> bool {
+        self >= tail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> StyleEngine {
+        let mut engine = StyleEngine::empty();
+        for (name, css) in [
+            ("p-4", "padding: 1rem"),
+            ("px-2", "padding-inline: 0.5rem"),
+            ("pl-1", "padding-inline-start: 0.25rem"),
+            ("flex", "display: flex"),
+            ("m-2", "margin: 0.5rem"),
+            ("inset-0", "inset: 0"),
+            ("top-0", "top: 0"),
+        ] {
+            engine.precompiled.insert(name.to_string(), css.to_string());
+        }
+        engine.screens.insert("sm".to_string(), "640px".to_string());
+        engine.screens.insert("md".to_string(), "48rem".to_string());
+        engine
+            .screens
+            .insert("lg".to_string(), "1024px".to_string());
+        engine
+            .states
+            .insert("hover".to_string(), ":hover".to_string());
+        engine
+            .states
+            .insert("focus".to_string(), ":focus".to_string());
+        engine
+    }
+
+    #[test]
+    fn base_then_states_then_breakpoints_by_width() {
+        let engine = engine();
+        let mut classes: Vec<String> = [
+            "lg:p-4",
+            "md:hover:px-2",
+            "md:p-4",
+            "focus:p-4",
+            "hover:p-4",
+            "px-2",
+            "sm:px-2",
+            "p-4",
+            "flex",
+            "top-0",
+            "inset-0",
+            "pl-1",
+            "m-2",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        sort_classes(&engine, &mut classes, &mut KeyCache::default());
+        assert_eq!(
+            classes,
+            [
+                "inset-0",
+                "top-0",
+                "flex",
+                "m-2",
+                "p-4",
+                "px-2",
+                "pl-1",
+                "hover:p-4",
+                "focus:p-4",
+                "sm:px-2",
+                "md:p-4",
+                "md:hover:px-2",
+                "lg:p-4",
+            ]
+        );
+    }
+
+    #[test]
+    fn max_ranges_come_before_breakpoints_and_features_last() {
+        let engine = engine();
+        let mut classes: Vec<String> = [
+            "print:p-4",
+            "md:max-lg:p-4",
+            "md:p-4",
+            "max-sm:p-4",
+            "max-lg:p-4",
+            "min-[700px]:p-4",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        sort_classes(&engine, &mut classes, &mut KeyCache::default());
+        assert_eq!(
+            classes,
+            [
+                "max-lg:p-4",
+                "max-sm:p-4",
+                "min-[700px]:p-4",
+                "md:p-4",
+                "md:max-lg:p-4",
+                "print:p-4",
+            ]
+        );
+    }
+
+    #[test]
+    fn parsed_selector_variants_sort_with_states() {
+        let engine = engine();
+        let mut classes: Vec<String> = [
+            "md:p-4",
+            "nth-[2n+1]:p-4",
+            "hover:p-4",
+            "aria-expanded:p-4",
+            "p-4",
+            "data-[state=open]:px-2",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        sort_classes(&engine, &mut classes, &mut KeyCache::default());
+        assert_eq!(
+            classes,
+            [
+                "p-4",
+                "hover:p-4",
+                "aria-expanded:p-4",
+                "nth-[2n+1]:p-4",
+                "data-[state=open]:px-2",
+                "md:p-4",
+            ]
+        );
+    }
+
+    #[test]
+    fn key_cache_keeps_only_the_sorted_classes() {
+        let engine = engine();
+        let mut cache = KeyCache::default();
+        let tail = cache.key(&engine, "md:p-4");
+        assert_eq!(tail, cascade_key(&engine, "md:p-4"));
+        let mut classes = vec!["px-2".to_string(), "p-4".to_string()];
+        sort_classes(&engine, &mut classes, &mut cache);
+        assert_eq!(classes, ["p-4", "px-2"]);
+        assert_eq!(cache.keys.len(), 2);
+        assert!(!cache.keys.contains_key("md:p-4"));
+    }
+
+    #[test]
+    fn appended_rules_must_follow_the_tail() {
+        let engine = engine();
+        let tail = cascade_key(&engine, "md:p-4");
+        assert!(cascade_key(&engine, "lg:p-4").follows(&tail));
+        assert!(!cascade_key(&engine, "p-4").follows(&tail));
+    }
+}
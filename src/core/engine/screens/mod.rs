@@ -1,6 +1,118 @@
 use ahash::AHashMap;
 
-pub fn build_block(selector: &str, declarations: &str) -> String {
+/// Media-feature variants that take no value.
+pub const MEDIA_FEATURES: &[(&str, &str)] = &[
+    ("portrait", "(orientation: portrait)"),
+    ("landscape", "(orientation: landscape)"),
+    ("motion-reduce", "(prefers-reduced-motion: reduce)"),
+    ("motion-safe", "(prefers-reduced-motion: no-preference)"),
+    ("contrast-more", "(prefers-contrast: more)"),
+    ("contrast-less", "(prefers-contrast: less)"),
+    ("forced-colors", "(forced-colors: active)"),
+];
+
+/// One variant that becomes part of an `@media` query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaVariant {
+    /// `md:` or `min-[900px]:`.
+    Min(String),
+    /// `max-md:` (exclusive, so `md:max-lg:` stops just below `lg`) or `max-[600px]:`
+    /// (inclusive, as written).
+    Max(String),
+    /// `print:`.
+    Type(&'static str),
+    /// A condition from `MEDIA_FEATURES`.
+    Feature(&'static str),
+}
+
+impl MediaVariant {
+    /// The bound in pixels for width variants whose value converts.
+    pub fn width_px(&self) -> Option<f64> {
+        match self {
+            MediaVariant::Min(v) | MediaVariant::Max(v) => length_px(v),
+            _ => None,
+        }
+    }
+}
+
+/// Resolves a variant against `screens.toml` and the built-in media variants.
+pub fn media_variant(screens: &AHashMap<String, String>, part: &str) -> Option<MediaVariant> {
+    if let Some(value) = screens.get(part) {
+        return Some(MediaVariant::Min(value.clone()));
+    }
+    if part == "print" {
+        return Some(MediaVariant::Type("print"));
+    }
+    if let Some((_, condition)) = MEDIA_FEATURES.iter().find(|(name, _)| *name == part) {
+        return Some(MediaVariant::Feature(condition));
+    }
+    if let Some(value) = arbitrary_width(part, "min-") {
+        return Some(MediaVariant::Min(value));
+    }
+    if let Some(value) = arbitrary_width(part, "max-") {
+        return Some(MediaVariant::Max(value));
+    }
+    let value = screens.get(part.strip_prefix("max-")?)?;
+    Some(MediaVariant::Max(exclusive_max(value)))
+}
+
+/// Upper bound that stops 0.02px short of `value`, so `max-md:` and `md:` never both apply.
+/// Values that do not convert to pixels are used as they are.
+pub fn exclusive_max(value: &str) -> String {
+    match length_px(value) {
+        Some(px) => format!("{}px", ((px - 0.02) * 100.0).round() / 100.0),
+        None => value.to_string(),
+    }
+}
+
+fn arbitrary_width(part: &str, prefix: &str) -> Option<String> {
+    let inner = part
+        .strip_prefix(prefix)?
+        .strip_prefix('[')?
+        .strip_suffix(']')?;
+    let value = crate::core::engine::arbitrary::decode_value(inner);
+    length_px(&value).map(|_| value)
+}
+
+/// Combines media variants into one `@media` rule, e.g. `@media print and (min-width: 768px)`.
+pub fn media_query(variants: &[MediaVariant]) -> String {
+    let mut parts: Vec<String> = Vec::with_capacity(variants.len());
+    if let Some(media_type) = variants.iter().find_map(|v| match v {
+        MediaVariant::Type(t) => Some(*t),
+        _ => None,
+    }) {
+        parts.push(media_type.to_string());
+    }
+    for variant in variants {
+        match variant {
+            MediaVariant::Min(v) => parts.push(format!("(min-width: {})", v)),
+            MediaVariant::Max(v) => parts.push(format!("(max-width: {})", v)),
+            MediaVariant::Feature(condition) => parts.push(condition.to_string()),
+            MediaVariant::Type(_) => {}
+        }
+    }
+    format!("@media {}", parts.join(" and "))
+}
+
+/// Converts `px`, `rem` and `em` lengths to pixels (16px per `rem`/`em`).
+pub fn length_px(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let (number, scale) = if let Some(n) = value.strip_suffix("rem") {
+        (n, 16.0)
+    } else if let Some(n) = value.strip_suffix("em") {
+        (n, 16.0)
+    } else if let Some(n) = value.strip_suffix("px") {
+        (n, 1.0)
+    } else {
+        (value, 1.0)
+    };
+    let n: f64 = number.trim().parse().ok()?;
+    (n.is_finite() && n >= 0.0).then_some(n * scale)
+}
+
+/// Writes one rule, keeping the last declaration of each property. `important` marks every
+/// declaration `!important` (for `!p-4` and `p-4!`).
+pub fn build_block(selector: &str, declarations: &str, important: bool) -> String {
     let decl_raw = declarations.trim().trim_end_matches(';').trim();
     let mut seen: AHashMap<&str, usize> = AHashMap::new();
     let parts: Vec<&str> = if decl_raw.is_empty() {
@@ -25,8 +137,12 @@ pub fn build_block(selector: &str, declarations: &str) -> String {
         }
         let name = pt.split(':').next().unwrap_or("").trim();
         if seen.get(name) == Some(&i) {
+            let decl = pt.trim_end_matches(';').trim_end();
             s.push_str("  ");
-            s.push_str(pt.trim_end_matches(';'));
+            s.push_str(decl);
+            if important && !decl.ends_with("!important") {
+                s.push_str(" !important");
+            }
             s.push_str(";\n");
         }
     }
@@ -63,3 +179,50 @@ pub fn wrap_media_queries(mut css_body: String, media_queries: &[String]) -> Str
     }
     css_body
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn important_marks_every_declaration_once() {
+        assert_eq!(
+            build_block(".x", "padding: 1rem; color: red !important", true),
+            ".x {\n  padding: 1rem !important;\n  color: red !important;\n}\n"
+        );
+        assert_eq!(
+            build_block(".x", "padding: 1rem", false),
+            ".x {\n  padding: 1rem;\n}\n"
+        );
+    }
+
+    #[test]
+    fn media_variants_cover_ranges_arbitrary_widths_and_features() {
+        let mut screens = AHashMap::new();
+        screens.insert("md".to_string(), "768px".to_string());
+        screens.insert("xl".to_string(), "80rem".to_string());
+        let query = |parts: &[&str]| {
+            let variants: Vec<MediaVariant> = parts
+                .iter()
+                .map(|p| media_variant(&screens, p).unwrap())
+                .collect();
+            media_query(&variants)
+        };
+        assert_eq!(query(&["max-md"]), "@media (max-width: 767.98px)");
+        assert_eq!(
+            query(&["md", "max-xl"]),
+            "@media (min-width: 768px) and (max-width: 1279.98px)"
+        );
+        assert_eq!(query(&["min-[900px]"]), "@media (min-width: 900px)");
+        assert_eq!(query(&["max-[37.5rem]"]), "@media (max-width: 37.5rem)");
+        assert_eq!(
+            query(&["motion-reduce", "print"]),
+            "@media print and (prefers-reduced-motion: reduce)"
+        );
+        assert_eq!(query(&["min-[0px]"]), "@media (min-width: 0px)");
+        assert_eq!(query(&["min-[0rem]"]), "@media (min-width: 0rem)");
+        assert_eq!(media_variant(&screens, "min-[wide]"), None);
+        assert_eq!(media_variant(&screens, "min-[-1px]"), None);
+        assert_eq!(media_variant(&screens, "max-2xl"), None);
+    }
+}
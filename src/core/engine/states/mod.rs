@@ -1,31 +1,575 @@
+use crate::config::ThemeConfig;
 use crate::core::engine::StyleEngine;
+use crate::core::engine::container_queries::{
+    ContainerVariant, container_queries, container_variant,
+};
+use crate::core::engine::screens::{MediaVariant, media_query, media_variant};
 use smallvec::SmallVec;
 
+/// Which element a relational variant (`group-*`, `peer-*`) watches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// An ancestor marked with `group` (or `group/<name>`).
+    Group,
+    /// A preceding sibling marked with `peer` (or `peer/<name>`).
+    Peer,
+}
+
+/// Splits `group-hover/card` into the relation, the state name and the optional group name.
+pub fn relational_variant(part: &str) -> Option<(Relation, &str, Option<&str>)> {
+    let (relation, rest) = if let Some(rest) = part.strip_prefix("group-") {
+        (Relation::Group, rest)
+    } else if let Some(rest) = part.strip_prefix("peer-") {
+        (Relation::Peer, rest)
+    } else {
+        return None;
+    };
+    match rest.split_once('/') {
+        Some((state, name)) if !state.is_empty() && !name.is_empty() => {
+            Some((relation, state, Some(name)))
+        }
+        Some(_) => None,
+        None if !rest.is_empty() => Some((relation, rest, None)),
+        None => None,
+    }
+}
+
+/// True for the marker classes relational variants point at (`group`, `peer/sidebar`).
+pub fn is_relation_marker(class: &str) -> bool {
+    let (marker, name) = match class.split_once('/') {
+        Some((marker, name)) => (marker, Some(name)),
+        None => (class, None),
+    };
+    matches!(marker, "group" | "peer") && name.is_none_or(|n| !n.is_empty())
+}
+
+/// Wrapper template for a `states.toml` entry applied to a group or peer instead of the element:
+/// `:hover` becomes `.group:hover &`, `&[open]` becomes `.peer[open] ~ &`.
+fn relational_wrapper(relation: Relation, state_value: &str, name: Option<&str>) -> String {
+    let mut marker = match relation {
+        Relation::Group => String::from(".group"),
+        Relation::Peer => String::from(".peer"),
+    };
+    if let Some(name) = name {
+        marker.push_str("\\/");
+        let _ = cssparser::serialize_name(name, &mut marker);
+    }
+    let target = if state_value.contains('&') {
+        state_value.replace('&', &marker)
+    } else {
+        format!("{}{}", marker, state_value)
+    };
+    match relation {
+        Relation::Group => format!("{} &", target),
+        Relation::Peer => format!("{} ~ &", target),
+    }
+}
+
+/// Selector suffix for variants parsed from their name rather than looked up in `states.toml`:
+/// `aria-expanded`, `aria-[sort=ascending]`, `data-[state=open]`, `has-[:checked]`, `has-checked`,
+/// `not-first`, `not-[.active]`, `nth-3`, `nth-[2n+1]` and `nth-last-2`.
+pub fn parsed_selector(engine: &StyleEngine, part: &str) -> Option<String> {
+    let selector = build_parsed_selector(engine, part)?;
+    // Arbitrary payloads must stay inside their own selector (`has-[a{}body{...}]` must not
+    // close the rule), so the built selector has to parse on its own.
+    (!part.contains('[') || is_valid_selector(&selector)).then_some(selector)
+}
+
+fn build_parsed_selector(engine: &StyleEngine, part: &str) -> Option<String> {
+    if let Some(rest) = part.strip_prefix("aria-") {
+        return Some(match arbitrary(rest) {
+            Some(inner) => attribute_selector("aria-", &inner),
+            None if is_name(rest) => format!("[aria-{}=\"true\"]", rest),
+            None => return None,
+        });
+    }
+    if let Some(rest) = part.strip_prefix("data-") {
+        return match arbitrary(rest) {
+            Some(inner) => Some(attribute_selector("data-", &inner)),
+            None if is_name(rest) => Some(format!("[data-{}]", rest)),
+            None => None,
+        };
+    }
+    if let Some(rest) = part.strip_prefix("has-") {
+        let inner = arbitrary(rest).or_else(|| state_selector(engine, rest))?;
+        return Some(format!(":has({})", inner));
+    }
+    if let Some(rest) = part.strip_prefix("not-") {
+        let inner = arbitrary(rest).or_else(|| state_selector(engine, rest))?;
+        return Some(format!(":not({})", inner));
+    }
+    if let Some(rest) = part.strip_prefix("nth-last-") {
+        return nth_argument(rest).map(|arg| format!(":nth-last-child({})", arg));
+    }
+    if let Some(rest) = part.strip_prefix("nth-") {
+        return nth_argument(rest).map(|arg| format!(":nth-child({})", arg));
+    }
+    None
+}
+
+/// A `states.toml` entry as a plain selector, for use inside `:has()`/`:not()`.
+fn state_selector(engine: &StyleEngine, name: &str) -> Option<String> {
+    engine
+        .states
+        .get(name)
+        .map(|value| value.trim_start_matches('&').trim().to_string())
+        .filter(|value| !value.is_empty() && !value.contains('&'))
+}
+
+/// The decoded payload of `[...]`, rejected when it could end the surrounding selector: rule
+/// delimiters, or brackets and parentheses that close more than they open.
+fn arbitrary(rest: &str) -> Option<String> {
+    let inner = rest.strip_prefix('[')?.strip_suffix(']')?;
+    let inner = crate::core::engine::arbitrary::decode_value(inner);
+    let mut depth = 0i32;
+    for c in inner.chars() {
+        match c {
+            '{' | '}' | ';' => return None,
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            return None;
+        }
+    }
+    (!inner.is_empty() && depth == 0).then_some(inner)
+}
+
+/// Checks a selector suffix built from an arbitrary variant with lightningcss.
+fn is_valid_selector(suffix: &str) -> bool {
+    use lightningcss::stylesheet::{ParserOptions, StyleSheet};
+    StyleSheet::parse(&format!("*{} {{}}", suffix), ParserOptions::default())
+        .is_ok_and(|sheet| sheet.rules.0.len() == 1)
+}
+
+fn is_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// `state=open` becomes `[data-state="open"]`; a bare name only checks the attribute exists.
+fn attribute_selector(prefix: &str, inner: &str) -> String {
+    match inner.split_once('=') {
+        Some((name, value)) => {
+            let value = value.trim();
+            let quoted = if value.starts_with('"') || value.starts_with('\'') {
+                value.to_string()
+            } else {
+                format!("\"{}\"", value.replace('"', "\\\""))
+            };
+            format!("[{}{}={}]", prefix, name.trim(), quoted)
+        }
+        None => format!("[{}{}]", prefix, inner.trim()),
+    }
+}
+
+fn nth_argument(rest: &str) -> Option<String> {
+    if let Some(inner) = arbitrary(rest) {
+        return Some(inner);
+    }
+    (!rest.is_empty() && rest.bytes().all(|b| b.is_ascii_digit())).then(|| rest.to_string())
+}
+
+/// Pseudo-element variants. `selection:` and `marker:` also reach descendants, so a list or a
+/// paragraph can be styled from its parent.
+const PSEUDO_ELEMENTS: &[(&str, &str)] = &[
+    ("before", "::before"),
+    ("after", "::after"),
+    ("placeholder", "::placeholder"),
+    ("selection", "::selection"),
+    ("marker", "::marker"),
+    ("file", "::file-selector-button"),
+    ("file-selector-button", "::file-selector-button"),
+    ("backdrop", "::backdrop"),
+    ("first-letter", "::first-letter"),
+    ("first-line", "::first-line"),
+];
+
+/// The pseudo-element a variant chain targets; the last one wins when several are given.
+pub fn pseudo_element(prefix_segment: &str) -> Option<&'static str> {
+    crate::core::engine::split_variants(prefix_segment)
+        .filter_map(|part| {
+            PSEUDO_ELEMENTS
+                .iter()
+                .find(|(name, _)| *name == part)
+                .map(|(_, element)| *element)
+        })
+        .last()
+}
+
+/// One way a `dark:`/`light:` variant can match: inside a media query, under a selector, or both.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemeCondition {
+    pub media: Option<&'static str>,
+    pub wrapper: Option<String>,
+}
+
+/// Conditions for `dark:` or `light:` under the configured `dark_mode`. With `both`, `dark:`
+/// matches either the selector or the media feature, so it has two conditions and
+/// `compute_css` emits one rule for each.
+pub fn color_scheme_conditions(theme: &ThemeConfig, dark: bool) -> Vec<SchemeCondition> {
+    let feature = if dark {
+        "(prefers-color-scheme: dark)"
+    } else {
+        "(prefers-color-scheme: light)"
+    };
+    let Some(selector) = theme.dark_selector() else {
+        return vec![SchemeCondition {
+            media: Some(feature),
+            wrapper: None,
+        }];
+    };
+    let wrapper = if dark {
+        format!("{} &", selector)
+    } else {
+        format!(":root:not({}) &", selector)
+    };
+    match (theme.follows_media(), dark) {
+        (true, true) => vec![
+            SchemeCondition {
+                media: None,
+                wrapper: Some(wrapper),
+            },
+            SchemeCondition {
+                media: Some(feature),
+                wrapper: None,
+            },
+        ],
+        (follows_media, _) => vec![SchemeCondition {
+            media: follows_media.then_some(feature),
+            wrapper: Some(wrapper),
+        }],
+    }
+}
+
+/// Number of rules a variant chain needs: more than one only when a `dark:`/`light:` variant
+/// has several conditions.
+pub fn color_scheme_passes(engine: &StyleEngine, prefix_segment: &str) -> usize {
+    crate::core::engine::split_variants(prefix_segment)
+        .filter(|part| matches!(*part, "dark" | "light"))
+        .map(|part| color_scheme_conditions(&engine.theme, part == "dark").len())
+        .max()
+        .unwrap_or(1)
+}
+
+/// True when `apply_wrappers_and_states` understands `part`.
+pub fn is_variant(engine: &StyleEngine, part: &str) -> bool {
+    let is_state =
+        |state: &str| engine.states.contains_key(state) || parsed_selector(engine, state).is_some();
+    media_variant(&engine.screens, part).is_some()
+        || container_variant(engine, part).is_some()
+        || is_state(part)
+        || relational_variant(part).is_some_and(|(_, state, _)| is_state(state))
+        || PSEUDO_ELEMENTS.iter().any(|(name, _)| *name == part)
+        || part
+            .strip_prefix("theme-")
+            .is_some_and(|name| engine.theme_lookup.contains_key(name))
+        || matches!(part, "*" | "dark" | "light")
+}
+
+/// First segment of a variant chain that is not a variant, such as `hovr` in `md:hovr`.
+pub fn unknown_variant<'a>(engine: &StyleEngine, prefix_segment: &'a str) -> Option<&'a str> {
+    crate::core::engine::split_variants(prefix_segment)
+        .filter(|part| !part.is_empty())
+        .find(|part| !is_variant(engine, part))
+}
+
+/// Variant names a typo can be corrected to: `states.toml` and `screens.toml` entries, `dark`,
+/// `light` and the pseudo-elements.
+pub fn variant_names(engine: &StyleEngine) -> impl Iterator<Item = &str> {
+    engine
+        .states
+        .keys()
+        .chain(engine.screens.keys())
+        .map(String::as_str)
+        .chain(["dark", "light"])
+        .chain(PSEUDO_ELEMENTS.iter().map(|(name, _)| *name))
+}
+
+/// Resolves a variant chain into media/container queries, pseudo-classes and selector wrappers.
+/// `scheme` picks which condition of a `dark:`/`light:` variant to use; see
+/// `color_scheme_passes`.
 pub fn apply_wrappers_and_states(
     engine: &StyleEngine,
     prefix_segment: &str,
+    scheme: usize,
 ) -> (SmallVec<[String; 4]>, String, SmallVec<[String; 2]>) {
     let mut media_queries: SmallVec<[String; 4]> = SmallVec::new();
     let mut pseudo_classes = String::new();
     let mut wrappers: SmallVec<[String; 2]> = SmallVec::new();
+    let mut media: SmallVec<[MediaVariant; 2]> = SmallVec::new();
+    let mut containers: SmallVec<[ContainerVariant; 1]> = SmallVec::new();
     if !prefix_segment.is_empty() {
-        for part in prefix_segment.split(':') {
-            if let Some(screen_value) = engine.screens.get(part) {
-                media_queries.push(format!("@media (min-width: {})", screen_value));
-            } else if let Some(cq_value) = engine.container_queries.get(part) {
-                media_queries.push(format!("@container (min-width: {})", cq_value));
+        for part in crate::core::engine::split_variants(prefix_segment) {
+            if let Some(variant) = media_variant(&engine.screens, part) {
+                media.push(variant);
+            } else if let Some(variant) = container_variant(engine, part) {
+                containers.push(variant);
             } else if let Some(state_value) = engine.states.get(part) {
                 if state_value.contains('&') {
                     wrappers.push(state_value.to_string());
                 } else {
                     pseudo_classes.push_str(state_value);
                 }
-            } else if part == "dark" {
-                wrappers.push(".dark &".to_string());
-            } else if part == "light" {
-                wrappers.push(":root &".to_string());
+            } else if let Some(suffix) = parsed_selector(engine, part) {
+                pseudo_classes.push_str(&suffix);
+            } else if let Some((relation, state_value, name)) =
+                relational_variant(part).and_then(|(r, state, name)| {
+                    let value = engine
+                        .states
+                        .get(state)
+                        .cloned()
+                        .or_else(|| parsed_selector(engine, state))?;
+                    Some((r, value, name))
+                })
+            {
+                wrappers.push(relational_wrapper(relation, &state_value, name));
+            } else if PSEUDO_ELEMENTS.iter().any(|(name, _)| *name == part) {
+                // Appended after everything else below.
+            } else if let Some(name) = part
+                .strip_prefix("theme-")
+                .filter(|name| engine.theme_lookup.contains_key(*name))
+            {
+                wrappers.push(format!(
+                    "{} &",
+                    crate::core::engine::theme_attribute_selector(name)
+                ));
+            } else if part == "*" {
+                wrappers.push(":where(& > *)".to_string());
+            } else if part == "dark" || part == "light" {
+                let conditions = color_scheme_conditions(&engine.theme, part == "dark");
+                if let Some(condition) = conditions.get(scheme).or(conditions.first()) {
+                    if let Some(feature) = condition.media {
+                        media.push(MediaVariant::Feature(feature));
+                    }
+                    if let Some(wrapper) = &condition.wrapper {
+                        wrappers.push(wrapper.clone());
+                    }
+                }
             }
         }
     }
+    // `wrap_media_queries` nests in order: the media query outermost, then each container.
+    if !media.is_empty() {
+        media_queries.push(media_query(&media));
+    }
+    media_queries.extend(container_queries(&containers));
+    // Wrappers nest in variant order, so `dark:group-hover:` needs both `.dark` and the hovered
+    // group rather than emitting one rule for each.
+    if wrappers.len() > 1 {
+        let composed = wrappers
+            .iter()
+            .fold(String::from("&"), |acc, w| acc.replace('&', w));
+        wrappers.clear();
+        wrappers.push(composed);
+    }
+    // Pseudo-elements must end the selector, after pseudo-classes and wrappers
+    // (`hover:before:` and `before:hover:` both give `.x:hover::before`).
+    if let Some(element) = pseudo_element(prefix_segment) {
+        let target = wrappers.pop().unwrap_or_else(|| String::from("&"));
+        let selector = match element {
+            "::selection" | "::marker" => format!("{0} *{1}, {0}{1}", target, element),
+            _ => format!("{}{}", target, element),
+        };
+        wrappers.push(selector);
+    }
     (media_queries, pseudo_classes, wrappers)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine() -> StyleEngine {
+        let mut engine = StyleEngine::empty();
+        for (name, value) in [
+            ("hover", ":hover"),
+            ("focus-within", ":focus-within"),
+            ("checked", ":checked"),
+            ("open", "&[open]"),
+        ] {
+            engine.states.insert(name.to_string(), value.to_string());
+        }
+        engine
+    }
+
+    #[test]
+    fn group_and_peer_variants_come_from_the_states_map() {
+        let engine = engine();
+        let wrapper = |prefix: &str| apply_wrappers_and_states(&engine, prefix, 0).2.to_vec();
+        assert_eq!(wrapper("group-hover"), [".group:hover &"]);
+        assert_eq!(wrapper("group-focus-within"), [".group:focus-within &"]);
+        assert_eq!(wrapper("peer-checked"), [".peer:checked ~ &"]);
+        assert_eq!(wrapper("group-open"), [".group[open] &"]);
+        assert_eq!(wrapper("group-hover/card"), [".group\\/card:hover &"]);
+        assert_eq!(wrapper("dark:peer-checked"), [".dark .peer:checked ~ &"]);
+        assert!(wrapper("group-unknown").is_empty());
+
+        let mut engine = engine;
+        engine.precompiled.insert(
+            "underline".to_string(),
+            "text-decoration-line: underline".to_string(),
+        );
+        let css = engine.css_for_class("group-hover/card:underline").unwrap();
+        assert!(css.starts_with(".group\\/card:hover .group-hover\\/card\\:underline {"));
+    }
+
+    #[test]
+    fn parsed_attribute_and_structural_variants() {
+        let mut engine = engine();
+        engine
+            .states
+            .insert("first".to_string(), ":first-child".to_string());
+        let suffix = |prefix: &str| apply_wrappers_and_states(&engine, prefix, 0).1;
+        assert_eq!(suffix("aria-expanded"), "[aria-expanded=\"true\"]");
+        assert_eq!(suffix("aria-[sort=ascending]"), "[aria-sort=\"ascending\"]");
+        assert_eq!(suffix("data-[state=open]"), "[data-state=\"open\"]");
+        assert_eq!(suffix("data-active"), "[data-active]");
+        assert_eq!(suffix("has-[:checked]"), ":has(:checked)");
+        assert_eq!(suffix("has-checked"), ":has(:checked)");
+        assert_eq!(suffix("not-first"), ":not(:first-child)");
+        assert_eq!(suffix("not-open"), ":not([open])");
+        assert_eq!(suffix("nth-3"), ":nth-child(3)");
+        assert_eq!(suffix("nth-[2n+1]"), ":nth-child(2n+1)");
+        assert_eq!(suffix("nth-last-2"), ":nth-last-child(2)");
+        assert_eq!(
+            suffix("hover:data-[state=open]"),
+            ":hover[data-state=\"open\"]"
+        );
+        assert_eq!(suffix("nth-x"), "");
+        assert_eq!(suffix("has-[h1,h2]"), ":has(h1,h2)");
+        assert_eq!(suffix("data-[state='open']"), "[data-state='open']");
+        assert_eq!(
+            apply_wrappers_and_states(&engine, "group-aria-expanded", 0)
+                .2
+                .to_vec(),
+            [".group[aria-expanded=\"true\"] &"]
+        );
+
+        engine
+            .precompiled
+            .insert("flex".to_string(), "display: flex".to_string());
+        engine.screens.insert("md".to_string(), "768px".to_string());
+        let css = engine.css_for_class("md:data-[state=open]:flex").unwrap();
+        assert!(css.starts_with("@media (min-width: 768px) {"));
+        assert!(css.contains(":flex[data-state=\"open\"] {"));
+        let css = engine.css_for_class("data-[state=open]:flex").unwrap();
+        assert!(css.starts_with(".data-\\[state\\=open\\]\\:flex[data-state=\"open\"] {"));
+        let css = engine.css_for_class("nth-[2n+1]:flex").unwrap();
+        assert!(css.starts_with(".nth-\\[2n\\+1\\]\\:flex:nth-child(2n+1) {"));
+    }
+
+    #[test]
+    fn arbitrary_variants_cannot_escape_the_selector() {
+        let mut engine = engine();
+        engine
+            .precompiled
+            .insert("p-4".to_string(), "padding: 1rem".to_string());
+        for part in [
+            "has-[a{}body{display:none}]",
+            "not-[a),body:hover]",
+            "data-[state=open],body[x]",
+            "aria-[label=\"x]",
+            "nth-[1);color:red]",
+        ] {
+            assert_eq!(parsed_selector(&engine, part), None, "{}", part);
+        }
+        assert!(
+            engine
+                .css_for_class("has-[a{}body{display:none}]:p-4")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn child_variant_and_important_modifier() {
+        let mut engine = engine();
+        engine
+            .precompiled
+            .insert("p-2".to_string(), "padding: 0.5rem".to_string());
+        assert_eq!(
+            engine.css_for_class("*:p-2").unwrap(),
+            ":where(.\\*\\:p-2 > *) {\n  padding: 0.5rem;\n}\n"
+        );
+        assert_eq!(
+            engine.css_for_class("dark:*:p-2!").unwrap(),
+            ".dark :where(.dark\\:\\*\\:p-2\\! > *) {\n  padding: 0.5rem !important;\n}\n"
+        );
+        assert_eq!(
+            engine.css_for_class("hover:!p-2").unwrap(),
+            ".hover\\:\\!p-2:hover {\n  padding: 0.5rem !important;\n}\n"
+        );
+    }
+
+    #[test]
+    fn pseudo_elements_end_the_selector() {
+        let mut engine = engine();
+        engine
+            .precompiled
+            .insert("block".to_string(), "display: block".to_string());
+        let wrapper = |prefix: &str| apply_wrappers_and_states(&engine, prefix, 0).2.to_vec();
+        assert_eq!(wrapper("before"), ["&::before"]);
+        assert_eq!(wrapper("group-hover:after"), [".group:hover &::after"]);
+        assert_eq!(wrapper("*:file"), [":where(& > *)::file-selector-button"]);
+        assert_eq!(wrapper("marker"), ["& *::marker, &::marker"]);
+
+        let css = engine.css_for_class("before:hover:block").unwrap();
+        assert_eq!(
+            css,
+            ".before\\:hover\\:block:hover::before {\n  content: var(--dx-content, '');\n  display: block;\n}\n"
+        );
+        let css = engine.css_for_class("hover:placeholder:block").unwrap();
+        assert!(
+            css.starts_with(".hover\\:placeholder\\:block:hover::placeholder {\n  display: block;")
+        );
+    }
+
+    #[test]
+    fn dark_variant_follows_dark_mode() {
+        use crate::config::DarkMode;
+        let mut engine = engine();
+        engine
+            .precompiled
+            .insert("block".to_string(), "display: block".to_string());
+        assert_eq!(
+            engine.css_for_class("dark:block").unwrap(),
+            ".dark .dark\\:block {\n  display: block;\n}\n"
+        );
+        engine.theme.dark_mode = DarkMode::Media;
+        assert_eq!(
+            engine.css_for_class("dark:block").unwrap(),
+            "@media (prefers-color-scheme: dark) {\n  .dark\\:block {\n    display: block;\n  }\n}\n"
+        );
+        engine.theme.dark_mode = DarkMode::Both;
+        engine.theme.dark_selector = Some("[data-mode=\"night\"]".to_string());
+        let css = engine.css_for_class("dark:block").unwrap();
+        assert!(css.starts_with("[data-mode=\"night\"] .dark\\:block {"));
+        assert!(css.contains("@media (prefers-color-scheme: dark) {\n  .dark\\:block {"));
+        let wrapper = |prefix: &str| apply_wrappers_and_states(&engine, prefix, 0).2.to_vec();
+        assert_eq!(wrapper("light"), [":root:not([data-mode=\"night\"]) &"]);
+    }
+
+    #[test]
+    fn theme_variant_targets_a_theme_block() {
+        let mut engine = engine();
+        engine
+            .theme_lookup
+            .insert("acme.high-contrast".to_string(), 0);
+        let wrapper = |prefix: &str| apply_wrappers_and_states(&engine, prefix, 0).2.to_vec();
+        assert_eq!(
+            wrapper("theme-acme.high-contrast:hover"),
+            ["[data-theme=\"acme.high-contrast\"] &"]
+        );
+        assert!(wrapper("theme-missing").is_empty());
+    }
+
+    #[test]
+    fn relation_markers() {
+        assert!(is_relation_marker("group"));
+        assert!(is_relation_marker("peer/sidebar"));
+        assert!(!is_relation_marker("group/"));
+        assert!(!is_relation_marker("grouped"));
+    }
+}
@@ -56,7 +56,13 @@ impl GroupRegistry {
         self.internal_tokens.contains(class)
     }
 
-    pub fn merge_preserve(&mut self, prev: &GroupRegistry) {
+    pub fn is_alias(&self, class: &str) -> bool {
+        self.definitions.contains_key(class)
+    }
+
+    /// `rename_similarity` is `[grouping] rename_similarity`: the Jaccard score an edited group
+    /// needs to keep its cached CSS under the new alias.
+    pub fn merge_preserve(&mut self, prev: &GroupRegistry, rename_similarity: f64) {
         for (name, def) in prev.definitions.iter() {
             self.definitions
                 .entry(name.clone())
@@ -129,12 +135,8 @@ impl GroupRegistry {
                             best_alias = Some(cand_alias.clone());
                         }
                     }
-                    let threshold: f64 = std::env::var("DX_GROUP_RENAME_SIMILARITY")
-                        .ok()
-                        .and_then(|s| s.parse().ok())
-                        .unwrap_or(0.6);
                     if let Some(new_alias) = best_alias {
-                        if best_score >= threshold {
+                        if best_score >= rename_similarity {
                             self.cached_css
                                 .entry(new_alias.clone())
                                 .or_insert_with(|| v.clone());
@@ -212,7 +214,18 @@ impl GroupRegistry {
         let mut known_prefixes: AHashSet<String> = AHashSet::default();
         if let Some(engine) = engine {
             known_prefixes.extend(engine.screens.keys().cloned());
+            known_prefixes.extend(engine.screens.keys().map(|s| format!("max-{}", s)));
+            known_prefixes.extend(
+                crate::core::engine::screens::MEDIA_FEATURES
+                    .iter()
+                    .map(|(name, _)| name.to_string()),
+            );
+            known_prefixes.insert("print".to_string());
             known_prefixes.extend(engine.states.keys().cloned());
+            for state in engine.states.keys() {
+                known_prefixes.insert(format!("group-{}", state));
+                known_prefixes.insert(format!("peer-{}", state));
+            }
             known_prefixes.extend(engine.container_queries.keys().cloned());
         }
 
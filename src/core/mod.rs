@@ -1,14 +1,25 @@
 use crate::{
-    cache, datasource, generator,
-    parser::{IncrementalParser, extract_classes_fast, rewrite_duplicate_classes},
+    cache,
+    config::{Config, GroupingConfig, ThemeConfig},
+    datasource::{
+        self,
+        content::{ContentIndex, merge_extracted},
+    },
+    generator,
+    parser::{
+        ExtractedClasses, IncrementalParser, extract_classes_fast, rewrite_duplicate_classes,
+    },
     telemetry::format_duration,
 };
 mod animation;
+pub mod diagnostics;
 mod engine;
+pub(crate) use engine::is_theme_token;
 mod formatter;
 pub mod group;
 use ahash::{AHashMap, AHashSet, AHasher};
 use colored::Colorize;
+use diagnostics::Diagnostic;
 use std::borrow::Cow;
 use std::hash::Hasher;
 pub mod color;
@@ -16,7 +27,7 @@ pub mod output;
 use cssparser::serialize_identifier;
 use output::CssOutput;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Instant;
 
 fn iter_class_attributes(html: &str) -> Vec<(String, String)> {
@@ -207,27 +218,199 @@ pub struct AppState {
     pub group_registry: group::GroupRegistry,
     pub group_log_hash: u64,
     pub incremental_parser: IncrementalParser,
+    pub content: ContentIndex,
+    /// Classes that generate no CSS; `None` until the first rebuild has checked every class.
+    pub unknown_classes: Option<AHashSet<String>>,
+    /// Cascade key of the last rule in the utilities layer. Additions that sort before it cannot
+    /// be appended without breaking the order, so they trigger a full rebuild.
+    pub utilities_tail: Option<engine::order::CascadeKey>,
+    pub cascade_keys: engine::order::KeyCache,
+    pub contrast_audits: diagnostics::contrast::AuditCache,
+}
+
+static THEME_CONFIG: OnceLock<ThemeConfig> = OnceLock::new();
+
+/// Sets the `[theme]` options the engine is loaded with. Only the first call has an effect, and
+/// only before the engine is first used.
+pub fn set_theme_config(theme: ThemeConfig) {
+    let _ = THEME_CONFIG.set(theme);
 }
 
 impl AppState {
     pub fn engine() -> &'static engine::StyleEngine {
-        use std::sync::OnceLock;
         static INSTANCE: OnceLock<engine::StyleEngine> = OnceLock::new();
         INSTANCE.get_or_init(|| {
-            engine::StyleEngine::load_from_disk().unwrap_or_else(|_| engine::StyleEngine::empty())
+            let mut engine = engine::StyleEngine::load_from_disk()
+                .unwrap_or_else(|_| engine::StyleEngine::empty());
+            engine.theme = THEME_CONFIG.get().cloned().unwrap_or_default();
+            engine
         })
     }
 }
 
+/// Switches for a single `rebuild_styles` call, derived from `Config` instead of process env.
+#[derive(Debug, Clone, Default)]
+pub struct RebuildOptions {
+    /// Rewrite the whole CSS file even when the class set is unchanged.
+    pub force_full: bool,
+    /// Run the formatting pass even when nothing changed.
+    pub force_format: bool,
+    /// Skip the per-rebuild timing line.
+    pub silent: bool,
+    pub incremental: bool,
+    pub debug: bool,
+    /// Check classes against the engine and return unknown-utility diagnostics.
+    pub diagnostics: bool,
+    /// Also audit text/background pairs on each element against these minimums.
+    pub contrast: Option<diagnostics::contrast::ContrastThresholds>,
+    pub grouping: GroupingConfig,
+}
+
+impl RebuildOptions {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            force_full: config.output.force_full,
+            force_format: false,
+            silent: false,
+            incremental: config.parser.incremental,
+            debug: config.parser.debug,
+            diagnostics: config.diagnostics.enabled,
+            contrast: config.diagnostics.contrast.then_some(
+                diagnostics::contrast::ContrastThresholds {
+                    min_ratio: config.diagnostics.min_contrast_ratio,
+                    min_lc: config.diagnostics.min_apca_lc,
+                },
+            ),
+            grouping: config.grouping.clone(),
+        }
+    }
+
+    /// Options for the delayed formatter's silent pass.
+    pub fn formatting(&self, force_write: bool) -> Self {
+        Self {
+            force_full: self.force_full || force_write,
+            force_format: true,
+            silent: true,
+            ..self.clone()
+        }
+    }
+}
+
+/// Updates `unknown_classes` from this rebuild's diff (or checks every cached class on the first
+/// call) and returns located diagnostics for all currently unknown classes, followed by the
+/// contrast audit of the index and content files when it is enabled.
+fn refresh_diagnostics(
+    state_guard: &mut AppState,
+    index_path: &str,
+    html_bytes: &[u8],
+    added: &[String],
+    removed: &[String],
+    options: &RebuildOptions,
+) -> Vec<Diagnostic> {
+    if !options.diagnostics {
+        return Vec::new();
+    }
+    let engine = AppState::engine();
+    let AppState {
+        unknown_classes,
+        class_cache,
+        group_registry,
+        content,
+        contrast_audits,
+        ..
+    } = state_guard;
+    let is_known = |class: &str| diagnostics::is_known(engine, group_registry, class);
+    let unknown = match unknown_classes {
+        Some(unknown) => {
+            for class in removed {
+                unknown.remove(class);
+            }
+            for class in added {
+                if !is_known(class) {
+                    unknown.insert(class.clone());
+                }
+            }
+            unknown
+        }
+        None => unknown_classes.insert(
+            class_cache
+                .iter()
+                .filter(|c| !is_known(c))
+                .cloned()
+                .collect(),
+        ),
+    };
+    if unknown.is_empty() && options.contrast.is_none() {
+        return Vec::new();
+    }
+    let html = String::from_utf8_lossy(html_bytes);
+    let index_display = diagnostics::display_path(std::path::Path::new(index_path));
+    let mut found = diagnostics::collect(engine, unknown.iter(), |class| {
+        if diagnostics::locate(&html, class).is_none()
+            && let Some(path) = content.source_of(class)
+            && let Ok(source) = std::fs::read_to_string(path)
+        {
+            return Some((diagnostics::display_path(path), source));
+        }
+        Some((index_display.clone(), html.to_string()))
+    });
+    if let Some(thresholds) = options.contrast {
+        found.extend(contrast_audits.refresh(
+            engine,
+            std::path::Path::new(index_path),
+            html_bytes,
+            content,
+            thresholds,
+        ));
+        diagnostics::sort(&mut found);
+    }
+    found
+}
+
+fn extract_with_content(
+    content: &ExtractedClasses,
+    html_bytes: &[u8],
+    capacity_hint: usize,
+) -> ExtractedClasses {
+    let mut extracted = extract_classes_fast(html_bytes, capacity_hint);
+    merge_extracted(&mut extracted, content);
+    extracted
+}
+
 pub fn rebuild_styles(
     state: Arc<Mutex<AppState>>,
     index_path: &str,
     is_initial_run: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+    options: &RebuildOptions,
+) -> Result<Vec<Diagnostic>, Box<dyn std::error::Error>> {
     // Check if we should suppress logging for this run (set by previous HTML grouping rewrite)
     let suppress_this_run = SUPPRESS_NEXT_LOG.swap(false, Ordering::Relaxed);
 
-    let mut html_bytes = datasource::read_file(index_path)?;
+    // The content globs are walked once, without the lock; after that the watcher reports
+    // changed files to the index itself.
+    let globs = {
+        let state_guard = state.lock().unwrap();
+        let content = &state_guard.content;
+        content.needs_walk().then(|| content.globs().clone())
+    };
+    let walked = globs.map(|globs| globs.walk());
+    let (content_fingerprint, content_extracted) = {
+        let mut state_guard = state.lock().unwrap();
+        if let Some(walked) = walked {
+            state_guard.content.refresh(walked);
+        }
+        let fingerprint =
+            (!state_guard.content.is_empty()).then(|| state_guard.content.fingerprint());
+        (fingerprint, state_guard.content.merged())
+    };
+
+    // With `content` globs the index file is optional.
+    let mut html_bytes =
+        if content_fingerprint.is_none() || std::path::Path::new(index_path).exists() {
+            datasource::read_file(index_path)?
+        } else {
+            Vec::new()
+        };
     let mut dev_group_selectors: AHashMap<String, String> = AHashMap::default();
     let mut html_was_rewritten = false;
 
@@ -259,18 +442,27 @@ pub fn rebuild_styles(
     let new_html_hash = {
         let mut hasher = AHasher::default();
         hasher.write(&html_bytes);
+        if let Some(fingerprint) = content_fingerprint {
+            hasher.write_u64(fingerprint);
+        }
         hasher.finish()
     };
     let hash_duration = hash_timer.elapsed();
 
-    let force_full = std::env::var("DX_FORCE_FULL").ok().as_deref() == Some("1")
-        || std::env::var("DX_FORCE_FORMAT").ok().as_deref() == Some("1");
+    let force_full = options.force_full || options.force_format;
     {
-        let state_guard = state.lock().unwrap();
+        let mut state_guard = state.lock().unwrap();
         let html_same = state_guard.html_hash == new_html_hash;
         let css_complete = state_guard.css_index.len() == state_guard.class_cache.len();
         if !force_full && html_same && (!is_initial_run || css_complete) {
-            return Ok(());
+            return Ok(refresh_diagnostics(
+                &mut state_guard,
+                index_path,
+                &html_bytes,
+                &[],
+                &[],
+                options,
+            ));
         }
     }
 
@@ -282,12 +474,17 @@ pub fn rebuild_styles(
         let mut state_guard = state.lock().unwrap();
         state_guard
             .incremental_parser
-            .parse_incremental(&html_bytes, prev_len_hint.next_power_of_two())
+            .set_enabled(options.incremental);
+        let mut extracted = state_guard
+            .incremental_parser
+            .parse_incremental(&html_bytes, prev_len_hint.next_power_of_two());
+        merge_extracted(&mut extracted, &content_extracted);
+        extracted
     };
     let parse_extract_duration = parse_timer.elapsed();
 
     // Log incremental parsing stats if enabled
-    if std::env::var("DX_DEBUG").ok().as_deref() == Some("1") {
+    if options.debug {
         let stats = {
             let state_guard = state.lock().unwrap();
             state_guard.incremental_parser.stats().clone()
@@ -344,10 +541,7 @@ pub fn rebuild_styles(
             }
         }
         if !current_defs_norm.is_empty() {
-            let threshold: f64 = std::env::var("DX_GROUP_RENAME_SIMILARITY")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(0.6);
+            let threshold = options.grouping.rename_similarity;
             let mut html_out = html_string_in.clone();
             let mut modified = false;
             let grouped_calls = find_grouped_calls_in_text(&html_string_in);
@@ -398,7 +592,7 @@ pub fn rebuild_styles(
                     if let Some(exact_alias) = current_defs_map.get(&sig) {
                         best_alias = Some(exact_alias.clone());
                         best_score = 1.0;
-                        if std::env::var("DX_DEBUG").ok().as_deref() == Some("1") {
+                        if options.debug {
                             eprintln!(
                                 "[dx-style-debug] exact-match fallback for @{} -> {} sig='{}'",
                                 old_name, exact_alias, sig
@@ -448,8 +642,11 @@ pub fn rebuild_styles(
             if modified {
                 std::fs::write(index_path, &html_out)?;
                 html_bytes = html_out.into_bytes();
-                let extracted2 =
-                    extract_classes_fast(&html_bytes, all_classes.len().next_power_of_two());
+                let extracted2 = extract_with_content(
+                    &content_extracted,
+                    &html_bytes,
+                    all_classes.len().next_power_of_two(),
+                );
                 let mut all_classes2 = extracted2.classes;
                 group_registry = group::GroupRegistry::analyze(
                     &extracted2.group_events,
@@ -536,17 +733,13 @@ pub fn rebuild_styles(
                         best_alias = Some(cand_alias.clone());
                     }
                 }
-                let threshold: f64 = std::env::var("DX_GROUP_RENAME_SIMILARITY")
-                    .ok()
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(0.6);
-                let allow_aggressive_env =
-                    std::env::var("DX_GROUP_AGGRESSIVE_REWRITE").ok().as_deref() == Some("1");
+                let threshold = options.grouping.rename_similarity;
+                let allow_aggressive_env = options.grouping.aggressive_rewrite;
                 if let Some(new_name) = best_alias {
                     if (best_score >= threshold && new_name.as_str() != old_name.as_str())
                         || allow_aggressive_env
                     {
-                        if std::env::var("DX_DEBUG").ok().as_deref() == Some("1") {
+                        if options.debug {
                             eprintln!(
                                 "[dx-style-debug] candidate rename: {} -> {} score={} threshold={} allow_aggressive={}",
                                 old_name, new_name, best_score, threshold, allow_aggressive_env
@@ -590,11 +783,7 @@ pub fn rebuild_styles(
                             modified = true;
                         }
 
-                        let overlap_threshold: f64 =
-                            std::env::var("DX_GROUP_REWRITE_UTILITY_OVERLAP")
-                                .ok()
-                                .and_then(|s| s.parse().ok())
-                                .unwrap_or(0.5);
+                        let overlap_threshold = options.grouping.utility_overlap;
                         let mut prev_set: AHashSet<String> = AHashSet::default();
                         if let Some(prev_def) =
                             prev_registry.definitions().find(|(n, _)| *n == old_name)
@@ -613,7 +802,7 @@ pub fn rebuild_styles(
                             }
                         }
                         if !prev_set.is_empty() {
-                            if std::env::var("DX_DEBUG").ok().as_deref() == Some("1") {
+                            if options.debug {
                                 eprintln!(
                                     "[dx-style-debug] prev_set for '{}' = {:?}",
                                     old_name, prev_set
@@ -634,7 +823,7 @@ pub fn rebuild_styles(
                                 }
                                 let overlap = (match_count as f64) / (total as f64);
                                 if overlap >= overlap_threshold && match_count > 0 {
-                                    if std::env::var("DX_DEBUG").ok().as_deref() == Some("1") {
+                                    if options.debug {
                                         eprintln!(
                                             "[dx-style-debug] class attr '{}' total={} match_count={} overlap={} -> will replace",
                                             classes_str, total, match_count, overlap
@@ -671,8 +860,11 @@ pub fn rebuild_styles(
             if modified {
                 std::fs::write(index_path, &html_string)?;
                 html_bytes = html_string.into_bytes();
-                let extracted2 =
-                    extract_classes_fast(&html_bytes, prev_len_hint.next_power_of_two());
+                let extracted2 = extract_with_content(
+                    &content_extracted,
+                    &html_bytes,
+                    prev_len_hint.next_power_of_two(),
+                );
                 let mut all_classes2 = extracted2.classes;
                 group_registry = group::GroupRegistry::analyze(
                     &extracted2.group_events,
@@ -681,7 +873,8 @@ pub fn rebuild_styles(
                 );
                 let prev_registry2 = { state.lock().unwrap().group_registry.clone() };
                 if prev_registry2.is_empty() == false && group_registry.is_empty() {
-                    group_registry.merge_preserve(&prev_registry2);
+                    group_registry
+                        .merge_preserve(&prev_registry2, options.grouping.rename_similarity);
                 }
                 all_classes = all_classes2;
             }
@@ -690,15 +883,11 @@ pub fn rebuild_styles(
     {
         let prev_registry = { state.lock().unwrap().group_registry.clone() };
         if prev_registry.is_empty() == false && group_registry.is_empty() {
-            group_registry.merge_preserve(&prev_registry);
+            group_registry.merge_preserve(&prev_registry, options.grouping.rename_similarity);
         }
     }
     {
-        if std::env::var("DX_GROUP_REWRITE_PLAIN_ALIAS")
-            .ok()
-            .as_deref()
-            == Some("1")
-        {
+        if options.grouping.rewrite_plain_alias {
             let html_string = String::from_utf8_lossy(&html_bytes).to_string();
             let mut new_html = html_string.clone();
             let mut any_mod = false;
@@ -725,8 +914,11 @@ pub fn rebuild_styles(
             if any_mod {
                 std::fs::write(index_path, &new_html)?;
                 html_bytes = new_html.into_bytes();
-                let extracted2 =
-                    extract_classes_fast(&html_bytes, all_classes.len().next_power_of_two());
+                let extracted2 = extract_with_content(
+                    &content_extracted,
+                    &html_bytes,
+                    all_classes.len().next_power_of_two(),
+                );
                 let mut all_classes2 = extracted2.classes;
                 group_registry = group::GroupRegistry::analyze(
                     &extracted2.group_events,
@@ -735,7 +927,8 @@ pub fn rebuild_styles(
                 );
                 let prev_registry = { state.lock().unwrap().group_registry.clone() };
                 if prev_registry.is_empty() == false && group_registry.is_empty() {
-                    group_registry.merge_preserve(&prev_registry);
+                    group_registry
+                        .merge_preserve(&prev_registry, options.grouping.rename_similarity);
                 }
                 all_classes = all_classes2;
             }
@@ -792,7 +985,11 @@ pub fn rebuild_styles(
         if modified {
             std::fs::write(index_path, &html_string)?;
             html_bytes = html_string.into_bytes();
-            let extracted2 = extract_classes_fast(&html_bytes, prev_len_hint.next_power_of_two());
+            let extracted2 = extract_with_content(
+                &content_extracted,
+                &html_bytes,
+                prev_len_hint.next_power_of_two(),
+            );
             let mut all_classes2 = extracted2.classes;
             group_registry = group::GroupRegistry::analyze(
                 &extracted2.group_events,
@@ -801,7 +998,7 @@ pub fn rebuild_styles(
             );
             let prev_registry = { state.lock().unwrap().group_registry.clone() };
             if prev_registry.is_empty() == false && group_registry.is_empty() {
-                group_registry.merge_preserve(&prev_registry);
+                group_registry.merge_preserve(&prev_registry, options.grouping.rename_similarity);
             }
             group_registry.remove_utility_members_from(&mut all_classes2);
             all_classes = all_classes2;
@@ -809,8 +1006,7 @@ pub fn rebuild_styles(
     }
 
     {
-        let aggressive_env =
-            std::env::var("DX_GROUP_AGGRESSIVE_REWRITE").ok().as_deref() == Some("1");
+        let aggressive_env = options.grouping.aggressive_rewrite;
         if aggressive_env {
             let mut group_sets: Vec<(String, AHashSet<String>)> = Vec::new();
             let mut alias_names: AHashSet<String> = AHashSet::default();
@@ -838,10 +1034,7 @@ pub fn rebuild_styles(
             }
 
             if !group_sets.is_empty() {
-                let overlap_threshold: f64 = std::env::var("DX_GROUP_REWRITE_UTILITY_OVERLAP")
-                    .ok()
-                    .and_then(|s| s.parse().ok())
-                    .unwrap_or(0.5);
+                let overlap_threshold = options.grouping.utility_overlap;
                 let html_string = String::from_utf8_lossy(&html_bytes).to_string();
                 let original_html_string = html_string.clone();
                 let mut new_html = html_string.clone();
@@ -899,7 +1092,7 @@ pub fn rebuild_styles(
                                 let new_attr = format!("class=\"{}\"", out_items.join(" "));
                                 new_html = new_html.replacen(&full, &new_attr, 1);
                                 any_mod = true;
-                                if std::env::var("DX_DEBUG").ok().as_deref() == Some("1") {
+                                if options.debug {
                                     eprintln!(
                                         "[dx-style-debug] aggressive replaced '{}' -> {}",
                                         full, new_attr
@@ -912,8 +1105,11 @@ pub fn rebuild_styles(
                 if any_mod {
                     std::fs::write(index_path, &new_html)?;
                     html_bytes = new_html.into_bytes();
-                    let extracted2 =
-                        extract_classes_fast(&html_bytes, all_classes.len().next_power_of_two());
+                    let extracted2 = extract_with_content(
+                        &content_extracted,
+                        &html_bytes,
+                        all_classes.len().next_power_of_two(),
+                    );
                     let mut all_classes2 = extracted2.classes;
                     group_registry = group::GroupRegistry::analyze(
                         &extracted2.group_events,
@@ -922,7 +1118,8 @@ pub fn rebuild_styles(
                     );
                     let prev_registry = { state.lock().unwrap().group_registry.clone() };
                     if prev_registry.is_empty() == false && group_registry.is_empty() {
-                        group_registry.merge_preserve(&prev_registry);
+                        group_registry
+                            .merge_preserve(&prev_registry, options.grouping.rename_similarity);
                     }
                     group_registry.remove_utility_members_from(&mut all_classes2);
                     all_classes = all_classes2;
@@ -985,8 +1182,11 @@ pub fn rebuild_styles(
         if modified {
             std::fs::write(index_path, &html_string)?;
             html_bytes = html_string.into_bytes();
-            let extracted2 =
-                extract_classes_fast(&html_bytes, all_classes.len().next_power_of_two());
+            let extracted2 = extract_with_content(
+                &content_extracted,
+                &html_bytes,
+                all_classes.len().next_power_of_two(),
+            );
             let mut all_classes2 = extracted2.classes;
             group_registry = group::GroupRegistry::analyze(
                 &extracted2.group_events,
@@ -1012,7 +1212,7 @@ pub fn rebuild_styles(
         let s = state.lock().unwrap();
         s.css_index.len() != s.class_cache.len()
     };
-    let force_format = std::env::var("DX_FORCE_FORMAT").ok().as_deref() == Some("1");
+    let force_format = options.force_format;
     if !force_format && added.is_empty() && removed.is_empty() && !css_incomplete {
         let mut state_guard = state.lock().unwrap();
         let mut h = AHasher::default();
@@ -1021,7 +1221,14 @@ pub fn rebuild_styles(
         }
         state_guard.class_list_checksum = h.finish();
         state_guard.html_hash = new_html_hash;
-        return Ok(());
+        return Ok(refresh_diagnostics(
+            &mut state_guard,
+            index_path,
+            &html_bytes,
+            &[],
+            &[],
+            options,
+        ));
     }
 
     let cache_update_timer = Instant::now();
@@ -1095,9 +1302,11 @@ pub fn rebuild_styles(
     let (css_write_duration, write_stats) = {
         let mut state_guard = state.lock().unwrap();
         state_guard.css_buffer.clear();
+        let style_engine = AppState::engine();
         let is_color = |c: &str| {
-            let base = c.rsplit(':').next().unwrap_or(c);
-            base.starts_with("bg-") || base.starts_with("text-")
+            let (lookup, _) = engine::strip_important(c);
+            crate::core::color::match_color_utility(style_engine, engine::split_class(&lookup).1)
+                .is_some()
         };
         let removed_has_color = removed.iter().any(|c| is_color(c));
         let added_has_color = added.iter().any(|c| is_color(c));
@@ -1106,10 +1315,22 @@ pub fn rebuild_styles(
             .any(|c| !state_guard.css_index.contains_key(c));
         let only_additions = !added.is_empty() && removed.is_empty();
         let only_removals = !removed.is_empty() && added.is_empty();
+        let mut added_keys: Vec<(engine::order::CascadeKey, &String)> = Vec::new();
+        if only_additions && !added_has_color {
+            added_keys = added
+                .iter()
+                .map(|c| (state_guard.cascade_keys.key(style_engine, c), c))
+                .collect();
+            added_keys.sort_by(|a, b| a.0.cmp(&b.0));
+        }
         let need_full = if force_full || is_initial_run {
             true
         } else if only_additions {
             added_has_color
+                || match (&state_guard.utilities_tail, added_keys.first()) {
+                    (Some(tail), Some((first, _))) => !first.follows(tail),
+                    _ => true,
+                }
         } else if only_removals {
             removed_has_color || missing_index_for_removed
         } else {
@@ -1117,7 +1338,14 @@ pub fn rebuild_styles(
         };
         if need_full {
             let mut class_vec: Vec<String> = state_guard.class_cache.iter().cloned().collect();
-            class_vec.sort();
+            engine::order::sort_classes(
+                style_engine,
+                &mut class_vec,
+                &mut state_guard.cascade_keys,
+            );
+            state_guard.utilities_tail = class_vec
+                .last()
+                .map(|c| state_guard.cascade_keys.key(style_engine, c));
             let phase_start = Instant::now();
             state_guard
                 .css_buffer
@@ -1283,24 +1511,9 @@ pub fn rebuild_styles(
             let frag_hash = hh.finish();
             let fragment_len = fragment_vec.len();
             let utilities_offset = state_guard.utilities_offset;
-            let mut wrote = false;
-            if state_guard.last_css_hash != frag_hash {
+            if state_guard.last_css_hash != frag_hash || options.force_full {
                 state_guard.css_out.replace(&fragment_vec)?;
                 state_guard.last_css_hash = frag_hash;
-                wrote = true;
-            } else if std::env::var("DX_FORCE_FULL").ok().as_deref() == Some("1") {
-                state_guard.css_out.replace(&fragment_vec)?;
-                state_guard.last_css_hash = frag_hash;
-                wrote = true;
-            }
-            if force_format {
-                unsafe {
-                    if wrote {
-                        std::env::set_var("DX_FORMAT_STATUS", "rewritten");
-                    } else {
-                        std::env::set_var("DX_FORMAT_STATUS", "unchanged");
-                    }
-                }
             }
             state_guard.css_index.clear();
             if utilities_offset >= fragment_len
@@ -1431,7 +1644,7 @@ pub fn rebuild_styles(
             let mut offsets: Vec<(String, usize, usize)> = Vec::with_capacity(added.len());
             let mut cursor_in_block = 1usize;
             let mut escaped = String::with_capacity(64);
-            for class in &added {
+            for &(_, class) in &added_keys {
                 if state_guard.group_registry.is_internal_token(class) {
                     continue;
                 }
@@ -1467,6 +1680,9 @@ pub fn rebuild_styles(
                 offsets.push((class.clone(), rule_start_block, rule_len));
             }
             let classes_written = offsets.len();
+            if let Some((last, _)) = added_keys.pop() {
+                state_guard.utilities_tail = Some(last);
+            }
             let gen_time = gen_start.elapsed();
             let build_time = std::time::Duration::from_micros(0);
             let flush_start = Instant::now();
@@ -1556,7 +1772,7 @@ pub fn rebuild_styles(
     // Suppress logging if:
     // 1. HTML was rewritten by grouping feature in THIS run (html_was_rewritten)
     // 2. This run was triggered by a previous HTML grouping rewrite (suppress_this_run)
-    let silent_format = std::env::var("DX_SILENT_FORMAT").ok().as_deref() == Some("1");
+    let silent_format = options.silent;
     let suppress_log = html_was_rewritten || suppress_this_run;
 
     if !suppress_log && !silent_format && !FIRST_LOG_DONE.load(Ordering::Relaxed) {
@@ -1622,5 +1838,13 @@ pub fn rebuild_styles(
         }
     }
 
-    Ok(())
+    let mut state_guard = state.lock().unwrap();
+    Ok(refresh_diagnostics(
+        &mut state_guard,
+        index_path,
+        &html_bytes,
+        &added,
+        &removed,
+        options,
+    ))
 }
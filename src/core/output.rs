@@ -2,9 +2,11 @@ use memmap2::MmapMut;
 use std::fs::{File, OpenOptions};
 use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 
 static mut MMAP_THRESHOLD_BYTES: u64 = 64 * 1024;
+static FLUSH_INTERVAL_MS: AtomicU64 = AtomicU64::new(25);
 
 const MANAGED_MARKER: &str = "/*
   ████████   ██             ██
@@ -313,15 +315,7 @@ impl CssOutput {
 
     #[allow(dead_code)]
     pub fn flush_if_dirty(&mut self) -> std::io::Result<()> {
-        use std::sync::OnceLock;
-        static FLUSH_INTERVAL: OnceLock<Duration> = OnceLock::new();
-        let interval = *FLUSH_INTERVAL.get_or_init(|| {
-            std::env::var("DX_FLUSH_INTERVAL_MS")
-                .ok()
-                .and_then(|v| v.parse::<u64>().ok())
-                .map(Duration::from_millis)
-                .unwrap_or_else(|| Duration::from_millis(25))
-        });
+        let interval = Duration::from_millis(FLUSH_INTERVAL_MS.load(Ordering::Relaxed));
         match &mut self.backend {
             CssBackend::Writer {
                 writer,
@@ -558,3 +552,7 @@ pub fn set_mmap_threshold(bytes: u64) {
         MMAP_THRESHOLD_BYTES = bytes;
     }
 }
+
+pub fn set_flush_interval_ms(ms: u64) {
+    FLUSH_INTERVAL_MS.store(ms, Ordering::Relaxed);
+}
@@ -0,0 +1,285 @@
+//! Per-file class tracking for the `content` globs in `.dx/config.toml`.
+//!
+//! Every matching file is parsed on its own and its classes are kept separately, so the merged
+//! set can be rebuilt when a file changes or disappears without touching the other files.
+
+use ahash::{AHashMap, AHashSet, AHasher};
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::glob::{GlobSet, relative_key};
+use crate::parser::{ExtractedClasses, ExtractorRegistry, GroupEvent};
+
+#[derive(Debug, Clone)]
+pub struct ContentFile {
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+    pub hash: u64,
+    pub classes: AHashSet<String>,
+    pub group_events: Vec<GroupEvent>,
+}
+
+#[derive(Debug, Default)]
+pub struct ContentIndex {
+    globs: GlobSet,
+    primary: Option<String>,
+    files: AHashMap<PathBuf, ContentFile>,
+    extractors: ExtractorRegistry,
+    /// Set by the first `refresh`; from then on watchers report changes through `update`.
+    walked: bool,
+}
+
+impl ContentIndex {
+    /// `primary` is the configured `index_file`; it is parsed by `rebuild_styles` itself (it can be
+    /// rewritten by grouping) and therefore skipped here even when a pattern matches it.
+    pub fn new<S: AsRef<str>>(patterns: &[S], primary: &str) -> Self {
+        Self {
+            globs: GlobSet::new(patterns),
+            primary: Some(relative_key(Path::new(primary))),
+            files: AHashMap::default(),
+            extractors: ExtractorRegistry::default(),
+            walked: false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty()
+    }
+
+    fn is_primary(&self, path: &Path) -> bool {
+        self.primary.as_deref() == Some(relative_key(path).as_str())
+    }
+
+    pub fn globs(&self) -> &GlobSet {
+        &self.globs
+    }
+
+    /// True until the globs have been walked once.
+    pub fn needs_walk(&self) -> bool {
+        !self.globs.is_empty() && !self.walked
+    }
+
+    /// Syncs the index with `walked`, the result of `globs().walk()` (taken by the caller so the
+    /// walk does not hold a lock on the index), and re-parses files whose size or mtime changed.
+    /// Returns true when any file was added, changed or removed.
+    pub fn refresh(&mut self, walked: Vec<PathBuf>) -> bool {
+        if self.globs.is_empty() {
+            return false;
+        }
+        self.walked = true;
+        let mut changed = false;
+        let current: Vec<PathBuf> = walked.into_iter().filter(|p| !self.is_primary(p)).collect();
+        let current_set: AHashSet<&PathBuf> = current.iter().collect();
+        let stale: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(|p| !current_set.contains(p))
+            .cloned()
+            .collect();
+        for path in stale {
+            self.files.remove(&path);
+            changed = true;
+        }
+        for path in current {
+            changed |= self.sync_file(&path);
+        }
+        changed
+    }
+
+    /// Re-reads only `paths`, e.g. the files a watcher event reported. Paths outside the globs
+    /// are ignored and files that no longer exist are dropped; new files are picked up as well,
+    /// but a full `refresh` is still needed after directories are created or moved.
+    pub fn update(&mut self, paths: &[PathBuf]) -> bool {
+        let mut changed = false;
+        for path in paths {
+            if !self.globs.matches(path) || self.is_primary(path) {
+                continue;
+            }
+            // Events report absolute paths; keep the key the walk used for a tracked file.
+            let key = relative_key(path);
+            let path = self
+                .files
+                .keys()
+                .find(|p| relative_key(p) == key)
+                .cloned()
+                .unwrap_or_else(|| path.clone());
+            if path.is_file() {
+                changed |= self.sync_file(&path);
+            } else {
+                changed |= self.files.remove(&path).is_some();
+            }
+        }
+        changed
+    }
+
+    /// Re-parses `path` when its size or mtime changed.
+    fn sync_file(&mut self, path: &Path) -> bool {
+        let meta = std::fs::metadata(path).ok();
+        let modified = meta.as_ref().and_then(|m| m.modified().ok());
+        let len = meta.as_ref().map(|m| m.len()).unwrap_or(0);
+        if let Some(existing) = self.files.get(path)
+            && existing.modified == modified
+            && existing.len == len
+        {
+            return false;
+        }
+        self.update_file(path, modified, len)
+    }
+
+    fn update_file(&mut self, path: &Path, modified: Option<SystemTime>, len: u64) -> bool {
+        let Ok(bytes) = super::read_file(path) else {
+            return self.files.remove(path).is_some();
+        };
+        let hash = {
+            let mut hasher = AHasher::default();
+            hasher.write(&bytes);
+            hasher.finish()
+        };
+        if let Some(existing) = self.files.get_mut(path) {
+            existing.modified = modified;
+            existing.len = len;
+            if existing.hash == hash {
+                return false;
+            }
+        }
+        let capacity_hint = self
+            .files
+            .get(path)
+            .map(|f| f.classes.len().next_power_of_two())
+            .unwrap_or(64);
+        let extracted = self.extractors.extract(path, &bytes, capacity_hint);
+        self.files.insert(
+            path.to_path_buf(),
+            ContentFile {
+                modified,
+                len,
+                hash,
+                classes: extracted.classes,
+                group_events: extracted.group_events,
+            },
+        );
+        true
+    }
+
+    /// Every tracked file with its content hash.
+    pub fn files(&self) -> impl Iterator<Item = (&Path, u64)> {
+        self.files.iter().map(|(p, f)| (p.as_path(), f.hash))
+    }
+
+    pub fn extractors(&self) -> &ExtractorRegistry {
+        &self.extractors
+    }
+
+    /// First tracked file (by path) that contains `class`.
+    pub fn source_of(&self, class: &str) -> Option<&Path> {
+        self.files
+            .iter()
+            .filter(|(_, f)| f.classes.contains(class))
+            .map(|(p, _)| p.as_path())
+            .min()
+    }
+
+    /// Order-independent hash over every tracked file's content hash.
+    pub fn fingerprint(&self) -> u64 {
+        let mut entries: Vec<(String, u64)> = self
+            .files
+            .iter()
+            .map(|(p, f)| (relative_key(p), f.hash))
+            .collect();
+        entries.sort();
+        let mut hasher = AHasher::default();
+        for (path, hash) in entries {
+            hasher.write(path.as_bytes());
+            hasher.write_u64(hash);
+        }
+        hasher.finish()
+    }
+
+    /// Union of every tracked file's classes and group events.
+    pub fn merged(&self) -> ExtractedClasses {
+        let total = self.files.values().map(|f| f.classes.len()).sum::<usize>();
+        let mut classes = AHashSet::with_capacity(total);
+        let mut group_events = Vec::new();
+        for file in self.files.values() {
+            classes.extend(file.classes.iter().cloned());
+            group_events.extend(file.group_events.iter().cloned());
+        }
+        ExtractedClasses {
+            classes,
+            group_events,
+        }
+    }
+}
+
+/// Adds the classes and group events of `extra` to `extracted`.
+pub fn merge_extracted(extracted: &mut ExtractedClasses, extra: &ExtractedClasses) {
+    extracted.classes.extend(extra.classes.iter().cloned());
+    extracted
+        .group_events
+        .extend(extra.group_events.iter().cloned());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removing_a_file_drops_only_its_classes() {
+        let dir = std::env::temp_dir().join(format!("dx_style_content_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("pages")).unwrap();
+        std::fs::write(dir.join("pages/a.html"), r#"<div class="flex p-4"></div>"#).unwrap();
+        std::fs::write(dir.join("pages/b.html"), r#"<div class="flex m-2"></div>"#).unwrap();
+
+        let pattern = format!("{}/**/*.html", dir.to_string_lossy().replace('\\', "/"));
+        let mut index = ContentIndex::new(&[pattern], "index.html");
+        assert!(index.refresh(index.globs().walk()));
+        let merged = index.merged();
+        assert!(merged.classes.contains("p-4"));
+        assert!(merged.classes.contains("m-2"));
+
+        std::fs::remove_file(dir.join("pages/b.html")).unwrap();
+        assert!(index.refresh(index.globs().walk()));
+        let merged = index.merged();
+        assert!(merged.classes.contains("flex"));
+        assert!(merged.classes.contains("p-4"));
+        assert!(!merged.classes.contains("m-2"));
+        assert!(
+            !index.refresh(index.globs().walk()),
+            "unchanged files are not re-parsed"
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn update_reads_only_the_reported_paths() {
+        let dir = std::env::temp_dir().join(format!("dx_style_update_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.html"), r#"<div class="p-4"></div>"#).unwrap();
+        std::fs::write(dir.join("b.html"), r#"<div class="m-2"></div>"#).unwrap();
+
+        let pattern = format!("{}/*.html", dir.to_string_lossy().replace('\\', "/"));
+        let mut index = ContentIndex::new(&[pattern], "index.html");
+        assert!(index.needs_walk());
+        index.refresh(index.globs().walk());
+        assert!(!index.needs_walk());
+
+        std::fs::write(dir.join("a.html"), r#"<div class="p-8"></div>"#).unwrap();
+        std::fs::write(dir.join("b.html"), r#"<div class="m-4 gap-2"></div>"#).unwrap();
+        std::fs::write(dir.join("c.txt"), r#"<div class="hidden"></div>"#).unwrap();
+        assert!(index.update(&[dir.join("a.html"), dir.join("c.txt")]));
+        let merged = index.merged();
+        assert!(merged.classes.contains("p-8") && !merged.classes.contains("p-4"));
+        assert!(merged.classes.contains("m-2"), "b.html was not reported");
+        assert!(!merged.classes.contains("hidden"));
+
+        std::fs::remove_file(dir.join("a.html")).unwrap();
+        assert!(index.update(&[dir.join("a.html")]));
+        assert!(!index.merged().classes.contains("p-8"));
+        assert_eq!(index.files().count(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
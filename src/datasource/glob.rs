@@ -0,0 +1,280 @@
+//! Minimal glob matching for the `content` patterns in `.dx/config.toml`.
+//!
+//! Supports `*`, `?`, `**` (any number of directories) and `{a,b}` alternation, which
+//! covers the Tailwind-style patterns projects actually use
+//! (`src/**/*.{html,jsx,tsx,vue,svelte}`).
+
+use std::path::{Path, PathBuf};
+
+/// Directory names that are never descended into while walking a pattern's base directory.
+const IGNORED_DIRS: &[&str] = &["node_modules", ".git", ".dx", "target"];
+
+#[derive(Debug, Clone)]
+struct GlobPattern {
+    segments: Vec<String>,
+    base: PathBuf,
+}
+
+impl GlobPattern {
+    fn new(pattern: &str) -> Self {
+        let normalized = normalize(pattern);
+        let segments: Vec<String> = normalized
+            .split('/')
+            .filter(|s| !s.is_empty() && *s != ".")
+            .map(|s| s.to_string())
+            .collect();
+        let mut base = PathBuf::new();
+        if normalized.starts_with('/') {
+            base.push("/");
+        }
+        for seg in &segments[..segments.len().saturating_sub(1)] {
+            if has_wildcard(seg) {
+                break;
+            }
+            base.push(seg);
+        }
+        if base.as_os_str().is_empty() {
+            base.push(".");
+        }
+        Self { segments, base }
+    }
+
+    fn matches(&self, rel: &str) -> bool {
+        let parts: Vec<&str> = rel.split('/').filter(|s| !s.is_empty()).collect();
+        match_segments(&self.segments, &parts)
+    }
+}
+
+/// A set of content globs, expanded from the raw config strings.
+#[derive(Debug, Clone, Default)]
+pub struct GlobSet {
+    patterns: Vec<GlobPattern>,
+}
+
+impl GlobSet {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        let mut out = Vec::new();
+        for raw in patterns {
+            for expanded in expand_braces(raw.as_ref().trim()) {
+                if !expanded.is_empty() {
+                    out.push(GlobPattern::new(&expanded));
+                }
+            }
+        }
+        Self { patterns: out }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Returns true if `path` (absolute or relative to the working directory) matches any pattern.
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let rel = relative_key(path);
+        self.patterns.iter().any(|p| p.matches(&rel))
+    }
+
+    /// Literal directory prefixes of every pattern, deduplicated. These are the roots a watcher
+    /// needs to observe.
+    pub fn base_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        for p in &self.patterns {
+            if !dirs.contains(&p.base) {
+                dirs.push(p.base.clone());
+            }
+        }
+        dirs
+    }
+
+    /// Walks every base directory and returns the matching files, sorted and deduplicated.
+    pub fn walk(&self) -> Vec<PathBuf> {
+        let mut out = Vec::new();
+        for dir in self.base_dirs() {
+            walk_dir(&dir, &mut |path| {
+                if self.matches(path) {
+                    out.push(path.to_path_buf());
+                }
+            });
+        }
+        out.sort();
+        out.dedup();
+        out
+    }
+}
+
+fn walk_dir(dir: &Path, visit: &mut dyn FnMut(&Path)) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if IGNORED_DIRS.iter().any(|d| *d == name) {
+                continue;
+            }
+            walk_dir(&path, visit);
+        } else if file_type.is_file() {
+            visit(&path);
+        }
+    }
+}
+
+/// Normalizes a path into the forward-slash, working-directory-relative form patterns are
+/// matched against.
+pub fn relative_key(path: &Path) -> String {
+    let rel = if path.is_absolute() {
+        std::env::current_dir()
+            .ok()
+            .and_then(|cwd| path.strip_prefix(&cwd).ok().map(|p| p.to_path_buf()))
+            .unwrap_or_else(|| path.to_path_buf())
+    } else {
+        path.to_path_buf()
+    };
+    normalize(&rel.to_string_lossy())
+}
+
+fn normalize(raw: &str) -> String {
+    let mut s = raw.replace('\\', "/");
+    while let Some(rest) = s.strip_prefix("./") {
+        s = rest.to_string();
+    }
+    s
+}
+
+fn has_wildcard(seg: &str) -> bool {
+    seg.bytes().any(|b| matches!(b, b'*' | b'?'))
+}
+
+/// Expands `{a,b}` alternations, including several groups in one pattern.
+pub fn expand_braces(pattern: &str) -> Vec<String> {
+    let bytes = pattern.as_bytes();
+    let Some(open) = bytes.iter().position(|b| *b == b'{') else {
+        return vec![pattern.to_string()];
+    };
+    let mut depth = 0usize;
+    let mut close = None;
+    for (i, b) in bytes.iter().enumerate().skip(open) {
+        match b {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+    let Some(close) = close else {
+        return vec![pattern.to_string()];
+    };
+    let prefix = &pattern[..open];
+    let suffix = &pattern[close + 1..];
+    let inner = &pattern[open + 1..close];
+    let mut alternatives = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0usize;
+    for (i, b) in inner.bytes().enumerate() {
+        match b {
+            b'{' => depth += 1,
+            b'}' => depth = depth.saturating_sub(1),
+            b',' if depth == 0 => {
+                alternatives.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    alternatives.push(&inner[start..]);
+    let mut out = Vec::new();
+    for alt in alternatives {
+        out.extend(expand_braces(&format!("{}{}{}", prefix, alt, suffix)));
+    }
+    out
+}
+
+fn match_segments(pattern: &[String], path: &[&str]) -> bool {
+    let Some((first, rest)) = pattern.split_first() else {
+        return path.is_empty();
+    };
+    if first == "**" {
+        for skip in 0..=path.len() {
+            if match_segments(rest, &path[skip..]) {
+                return true;
+            }
+        }
+        return false;
+    }
+    match path.split_first() {
+        Some((head, tail)) => {
+            match_wildcard(first.as_bytes(), head.as_bytes()) && match_segments(rest, tail)
+        }
+        None => false,
+    }
+}
+
+fn match_wildcard(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0usize, 0usize);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    while p < pattern.len() && pattern[p] == b'*' {
+        p += 1;
+    }
+    p == pattern.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn braces_expand_to_every_alternative() {
+        let expanded = expand_braces("src/**/*.{html,jsx,tsx}");
+        assert_eq!(
+            expanded,
+            vec!["src/**/*.html", "src/**/*.jsx", "src/**/*.tsx"]
+        );
+    }
+
+    #[test]
+    fn double_star_matches_any_depth() {
+        let set = GlobSet::new(&["src/**/*.{html,vue}"]);
+        assert!(set.matches(Path::new("src/index.html")));
+        assert!(set.matches(Path::new("./src/pages/blog/post.vue")));
+        assert!(set.matches(Path::new("src\\components\\card.html")));
+        assert!(!set.matches(Path::new("src/main.rs")));
+        assert!(!set.matches(Path::new("other/index.html")));
+    }
+
+    #[test]
+    fn base_dirs_stop_at_first_wildcard() {
+        let set = GlobSet::new(&["app/routes/*/page.tsx", "*.html"]);
+        assert_eq!(
+            set.base_dirs(),
+            vec![PathBuf::from("app/routes"), PathBuf::from(".")]
+        );
+    }
+}
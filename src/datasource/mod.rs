@@ -1,3 +1,6 @@
+pub mod content;
+pub mod glob;
+
 use std::fs;
 use std::io;
 use std::path::Path;
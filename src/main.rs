@@ -5,6 +5,7 @@ use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 mod cache;
+mod cli;
 mod config;
 mod core;
 mod datasource;
@@ -13,8 +14,13 @@ mod parser;
 mod telemetry;
 mod watcher;
 
+use crate::cli::{Cli, Command};
 use crate::config::Config;
-use core::{AppState, rebuild_styles, set_base_layer_present, set_properties_layer_present};
+use crate::core::diagnostics::{Diagnostic, Reporter, display_path};
+use colored::Colorize;
+use core::{
+    AppState, RebuildOptions, rebuild_styles, set_base_layer_present, set_properties_layer_present,
+};
 use lightningcss::stylesheet::{ParserOptions, StyleSheet};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -25,7 +31,7 @@ fn start_delayed_formatter(
     initial_delay_ms: u64,
     interval_ms: u64,
     debounce_ms: u64,
-    force_write: bool,
+    options: RebuildOptions,
 ) {
     if interval_ms == 0 {
         return;
@@ -70,48 +76,324 @@ fn start_delayed_formatter(
             last_run = Instant::now();
             last_classes_checksum = current_checksum;
             let start = Instant::now();
-            unsafe {
-                std::env::set_var("DX_FORCE_FORMAT", "1");
-                std::env::set_var("DX_SILENT_FORMAT", "1");
-                if force_write {
-                    std::env::set_var("DX_FORCE_FULL", "1");
-                }
-            }
-            let _ = rebuild_styles(state.clone(), &index_path, false);
-            unsafe {
-                std::env::remove_var("DX_FORCE_FORMAT");
-                std::env::remove_var("DX_SILENT_FORMAT");
-                if force_write {
-                    std::env::remove_var("DX_FORCE_FULL");
-                }
-            }
+            let _ = rebuild_styles(state.clone(), &index_path, false, &options);
             let _dur = start.elapsed();
             std::thread::sleep(Duration::from_millis(50));
         }
     });
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = Config::load().unwrap_or_else(|_| Config::default());
+fn main() {
+    let cli = match Cli::from_env() {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{} {}\n\n{}", "error:".red(), e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = run(&cli) {
+        eprintln!("{} {}", "error:".red(), e);
+        std::process::exit(1);
+    }
+}
 
+fn run(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    if cli.command == Command::Help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+    let config = cli.load_config()?;
+    match cli.command {
+        Command::PrintConfig => {
+            print!("{}", toml::to_string_pretty(&config)?);
+            Ok(())
+        }
+        Command::Clean => clean(&config),
+        Command::Build => {
+            let state = init_state(&config)?;
+            let options = RebuildOptions::from_config(&config);
+            let diagnostics = rebuild_styles(state, &config.paths.index_file, true, &options)?;
+            enforce_diagnostics(&config, &diagnostics)
+        }
+        Command::Check => {
+            let (up_to_date, diagnostics) = check(&config)?;
+            enforce_diagnostics(&config, &diagnostics)?;
+            if !up_to_date {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Command::ThemeFromImage => theme_from_image(cli),
+        Command::ThemeAudit => theme_audit(&config, cli),
+        Command::Watch | Command::Help => watch(config),
+    }
+}
+
+/// Prints every diagnostic; in strict mode any diagnostic fails the command.
+fn enforce_diagnostics(
+    config: &Config,
+    diagnostics: &[Diagnostic],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let strict = config.diagnostics.strict;
+    let label = if strict {
+        "error:".red()
+    } else {
+        "warning:".yellow()
+    };
+    for diagnostic in diagnostics {
+        eprintln!("{} {}", label, diagnostic);
+    }
+    if strict && !diagnostics.is_empty() {
+        return Err(format!(
+            "{} {} (diagnostics.strict)",
+            diagnostics.len(),
+            if diagnostics.len() == 1 {
+                "diagnostic"
+            } else {
+                "diagnostics"
+            }
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Lists the best-scoring seed colors of an image and prints the config that uses the first.
+#[cfg(feature = "image")]
+fn theme_from_image(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::core::color::{hct::Hct, image::ImageReader};
+    let path = cli
+        .image
+        .as_deref()
+        .ok_or("theme from-image needs an image path")?;
+    let colors = ImageReader::source_colors(path, cli.candidates.unwrap_or(1))?;
+    let seed = colors
+        .first()
+        .ok_or_else(|| format!("no colors found in {}", path))?;
+    for (rank, color) in colors.iter().enumerate() {
+        let hct = Hct::new(*color);
+        println!(
+            "{:>2}. {} {}  hue {:>3.0}  chroma {:>3.0}  tone {:>3.0}",
+            rank + 1,
+            "    ".on_truecolor(color.red, color.green, color.blue),
+            color.to_hex_with_pound(),
+            hct.get_hue(),
+            hct.get_chroma(),
+            hct.get_tone()
+        );
+    }
+    println!(
+        "\n[theme]\nsource = \"{}\"   # or source_image = \"{}\"",
+        seed.to_hex_with_pound(),
+        path
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "image"))]
+fn theme_from_image(_cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    Err("theme from-image needs dx-style built with the `image` feature".into())
+}
+
+/// Simulates color vision deficiencies on the theme tokens and lists the role pairs that become
+/// hard to tell apart.
+fn theme_audit(config: &Config, cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::core::color::{
+        color::Argb,
+        cvd::{self, Deficiency},
+    };
+    configure_engine(config)?;
+    let min_distance = cli.min_distance.unwrap_or(8.0);
+    let colors = core::AppState::engine().theme_colors();
+    let pairs = colors.distinct_role_pairs();
+    let swatch = |color: Argb| "  ".on_truecolor(color.red, color.green, color.blue);
+    let mut collapsed_count = 0;
+    for (scheme, dark) in [("light", false), ("dark", true)] {
+        let tokens: Vec<(String, Argb)> = colors
+            .token_names()
+            .into_iter()
+            .filter_map(|name| colors.resolve(&name, dark).map(|color| (name, color)))
+            .collect();
+        let collapsed = cvd::collapsed_pairs(&tokens, &pairs, min_distance);
+        println!("{} ({} tokens)", scheme.bold(), tokens.len());
+        if collapsed.is_empty() {
+            println!("  every role pair stays at least {:.1} apart", min_distance);
+        }
+        for pair in &collapsed {
+            let color = |name: &str| {
+                let (_, color) = tokens.iter().find(|(token, _)| token == name).unwrap();
+                cvd::simulate(*color, pair.deficiency)
+            };
+            println!(
+                "  {:<14} {}{} {:<24} {:>5.1}  (normal vision {:.1})",
+                pair.deficiency.name(),
+                swatch(color(&pair.first)),
+                swatch(color(&pair.second)),
+                format!("{} / {}", pair.first, pair.second),
+                pair.distance,
+                pair.normal_distance
+            );
+        }
+        collapsed_count += collapsed.len();
+    }
+    println!(
+        "\n{} collapsed pair{} below {:.1} (CAM16-UCS ΔE', {})",
+        collapsed_count,
+        if collapsed_count == 1 { "" } else { "s" },
+        min_distance,
+        Deficiency::ALL.map(Deficiency::name).join(", ")
+    );
+    Ok(())
+}
+
+fn clean(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = config.resolved_cache_dir();
+    if Path::new(dir).exists() {
+        std::fs::remove_dir_all(dir)?;
+        println!("Removed {}", dir);
+    } else {
+        println!("Nothing to clean in {}", dir);
+    }
+    Ok(())
+}
+
+/// Runs a full build against copies of the index and CSS files in a scratch directory and
+/// compares the result with the real CSS file. Nothing in the project is written.
+fn check(config: &Config) -> Result<(bool, Vec<Diagnostic>), Box<dyn std::error::Error>> {
+    let scratch = std::env::temp_dir().join(format!("dx-style-check-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&scratch);
+    std::fs::create_dir_all(&scratch)?;
+    let result = check_in(config, &scratch);
+    let _ = std::fs::remove_dir_all(&scratch);
+    result
+}
+
+fn check_in(
+    config: &Config,
+    scratch: &Path,
+) -> Result<(bool, Vec<Diagnostic>), Box<dyn std::error::Error>> {
+    let scratch_file = |original: &str, fallback: &str| -> std::io::Result<String> {
+        let name = Path::new(original)
+            .file_name()
+            .map(|n| n.to_os_string())
+            .unwrap_or_else(|| fallback.into());
+        let target = scratch.join(name);
+        if Path::new(original).exists() {
+            std::fs::copy(original, &target)?;
+        }
+        Ok(target.to_string_lossy().into_owned())
+    };
+    let mut scratch_config = config.clone();
+    scratch_config.paths.index_file = scratch_file(&config.paths.index_file, "index.html")?;
+    scratch_config.paths.css_file = scratch_file(&config.paths.css_file, "style.css")?;
+    scratch_config.paths.cache_dir = Some(scratch.join("cache").to_string_lossy().into_owned());
+
+    let state = init_state(&scratch_config)?;
+    let options = RebuildOptions::from_config(&scratch_config);
+    let mut diagnostics = rebuild_styles(
+        state.clone(),
+        &scratch_config.paths.index_file,
+        true,
+        &options,
+    )?;
+    drop(state);
+    let scratch_index = display_path(Path::new(&scratch_config.paths.index_file));
+    for diagnostic in &mut diagnostics {
+        if diagnostic.file == scratch_index {
+            diagnostic.file = display_path(Path::new(&config.paths.index_file));
+        }
+    }
+
+    let trim = |mut bytes: Vec<u8>| {
+        while bytes.last() == Some(&0) {
+            bytes.pop();
+        }
+        bytes
+    };
+    let expected = trim(std::fs::read(&scratch_config.paths.css_file)?);
+    let actual = trim(std::fs::read(&config.paths.css_file).unwrap_or_default());
+    if expected == actual {
+        println!("{} is up to date", config.paths.css_file);
+        Ok((true, diagnostics))
+    } else {
+        eprintln!(
+            "{} {} is out of date; run `dx-style build`",
+            "error:".red(),
+            config.paths.css_file
+        );
+        Ok((false, diagnostics))
+    }
+}
+
+fn watch(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    let app_state = init_state(&config)?;
+
+    if std::env::var("DX_DUMP_STATE_ON_START").is_ok() {
+        let s = app_state.lock().unwrap();
+        let dump = serde_json::json!({
+            "html_hash": s.html_hash,
+            "class_cache_len": s.class_cache.len()
+        });
+        println!("{}", dump.to_string());
+        return Ok(());
+    }
+
+    let options = RebuildOptions::from_config(&config);
+    let mut reporter = Reporter::default();
+    reporter.report(&rebuild_styles(
+        app_state.clone(),
+        &config.paths.index_file,
+        true,
+        &options,
+    )?);
+    start_delayed_formatter(
+        app_state.clone(),
+        config.paths.index_file.clone(),
+        config.format_delay_ms(),
+        config.format_interval_ms(),
+        config.format_debounce_ms(),
+        options.formatting(config.format_force_write()),
+    );
+
+    start_css_validator(
+        app_state.clone(),
+        config.paths.index_file.clone(),
+        options.clone(),
+    );
+
+    watcher::start(app_state, config, reporter)?;
+
+    Ok(())
+}
+
+/// Points the engine at the configured `style.bin` and theme before its first use.
+fn configure_engine(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
     unsafe {
-        std::env::set_var("DX_CACHE_DIR", config.resolved_cache_dir());
         let style_bin = format!("{}/style.bin", config.resolved_style_dir());
         std::env::set_var("DX_STYLE_BIN", style_bin);
     }
+    let mut theme = config.theme.clone();
+    theme.resolve_source_image()?;
+    core::set_theme_config(theme);
+    Ok(())
+}
+
+/// Creates missing project files and loads the cache and existing CSS into a fresh `AppState`.
+fn init_state(config: &Config) -> Result<Arc<Mutex<AppState>>, Box<dyn std::error::Error>> {
+    unsafe {
+        std::env::set_var("DX_CACHE_DIR", config.resolved_cache_dir());
+    }
+    configure_engine(config)?;
 
     if !Path::new(&config.paths.css_file).exists() {
         File::create(&config.paths.css_file)?;
     }
-    if !Path::new(&config.paths.index_file).exists() {
+    if config.content.is_empty() && !Path::new(&config.paths.index_file).exists() {
         File::create(&config.paths.index_file)?;
     }
 
-    if let Ok(val) = std::env::var("DX_MMAP_THRESHOLD") {
-        if let Ok(parsed) = val.parse::<u64>() {
-            core::output::set_mmap_threshold(parsed);
-        }
-    }
+    core::output::set_mmap_threshold(config.output.mmap_threshold);
+    core::output::set_flush_interval_ms(config.output.flush_interval_ms);
     let css_out = core::output::CssOutput::open(&config.paths.css_file)?;
 
     let (preloaded_cache, preloaded_hash, preloaded_checksum, preloaded_groups) =
@@ -222,7 +504,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
     }
-    let app_state = Arc::new(Mutex::new(AppState {
+    Ok(Arc::new(Mutex::new(AppState {
         html_hash: preloaded_hash,
         class_cache: preloaded_cache,
         css_out,
@@ -238,36 +520,22 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         },
         group_log_hash: 0,
         incremental_parser: crate::parser::IncrementalParser::new(),
-    }));
-
-    if std::env::var("DX_DUMP_STATE_ON_START").is_ok() {
-        let s = app_state.lock().unwrap();
-        let dump = serde_json::json!({
-            "html_hash": s.html_hash,
-            "class_cache_len": s.class_cache.len()
-        });
-        println!("{}", dump.to_string());
-        return Ok(());
-    }
-
-    rebuild_styles(app_state.clone(), &config.paths.index_file, true)?;
-    start_delayed_formatter(
-        app_state.clone(),
-        config.paths.index_file.clone(),
-        config.format_delay_ms(),
-        config.format_interval_ms(),
-        config.format_debounce_ms(),
-        config.format_force_write(),
-    );
-
-    start_css_validator(app_state.clone());
-
-    watcher::start(app_state, config)?;
-
-    Ok(())
+        content: crate::datasource::content::ContentIndex::new(
+            &config.content,
+            &config.paths.index_file,
+        ),
+        unknown_classes: None,
+        utilities_tail: None,
+        cascade_keys: Default::default(),
+        contrast_audits: Default::default(),
+    })))
 }
 
-fn start_css_validator(state: Arc<Mutex<AppState>>) {
+fn start_css_validator(state: Arc<Mutex<AppState>>, index_file: String, options: RebuildOptions) {
+    let options = RebuildOptions {
+        force_format: true,
+        ..options
+    };
     const INTERVAL: Duration = Duration::from_millis(1500);
     thread::spawn(move || {
         let mut last_check = Instant::now() - INTERVAL;
@@ -347,10 +615,7 @@ fn start_css_validator(state: Arc<Mutex<AppState>>) {
                 }
                 i += 1;
             }
-            let validator_log = std::env::var("DX_VALIDATOR_LOG")
-                .ok()
-                .map(|v| v == "1")
-                .unwrap_or(false);
+            let validator_log = options.debug;
             if strict_ok {
                 if let Some((_, last_end)) = layer_ranges.iter().max_by_key(|r| r.1) {
                     let trailing = &text[*last_end..];
@@ -366,18 +631,7 @@ fn start_css_validator(state: Arc<Mutex<AppState>>) {
                 if validator_log {
                     eprintln!("[validator] generator error -> forcing rebuild");
                 }
-                unsafe {
-                    std::env::set_var("DX_FORCE_FORMAT", "1");
-                }
-                let index_file = if let Ok(cfg) = crate::config::Config::load() {
-                    cfg.paths.index_file
-                } else {
-                    "index.html".to_string()
-                };
-                let _ = rebuild_styles(state.clone(), &index_file, true);
-                unsafe {
-                    std::env::remove_var("DX_FORCE_FORMAT");
-                }
+                let _ = rebuild_styles(state.clone(), &index_file, true, &options);
             } else {
                 if validator_log {
                     eprintln!("[validator] user manual error -> commenting out invalid part");
@@ -0,0 +1,652 @@
+//! Per-language class extractors.
+//!
+//! `extract_classes_fast` only understands plain `class="..."` attributes. Component files keep
+//! their classes in framework syntax (`className={cn(...)}`, Vue `:class` objects, Svelte
+//! `class:` directives, Astro `class:list`), so each file extension can pick a
+//! [`ClassExtractor`] that knows where to look. Unknown extensions fall back to the HTML scanner.
+
+use ahash::{AHashMap, AHashSet};
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{ExtractedClasses, GroupCollector, expand_grouping_into, extract_classes_fast};
+
+/// Helper functions whose string arguments are treated as class lists wherever they are called.
+const CLASS_HELPERS: &[&str] = &[
+    "clsx",
+    "cn",
+    "classnames",
+    "classNames",
+    "twMerge",
+    "twJoin",
+];
+
+/// Marks the position of an interpolation inside a literal so partial tokens such as
+/// `p-${size}` can be dropped.
+const HOLE: char = '\u{1}';
+
+pub trait ClassExtractor: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn extract(&self, source: &[u8], capacity_hint: usize) -> ExtractedClasses;
+
+    /// The literal class list of each element with the byte offset of its value, for checks that
+    /// need an element's classes together (the contrast audit).
+    fn element_class_lists(&self, source: &[u8]) -> Vec<(usize, Vec<String>)> {
+        super::element_class_lists(source)
+    }
+}
+
+/// The default scanner: `class="..."` and `dx-*="..."` attributes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct HtmlExtractor;
+
+impl ClassExtractor for HtmlExtractor {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn extract(&self, source: &[u8], capacity_hint: usize) -> ExtractedClasses {
+        extract_classes_fast(source, capacity_hint)
+    }
+}
+
+/// JSX/TSX: `className`/`class` attributes (quoted or `{expression}`) and class helper calls.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsxExtractor;
+
+impl ClassExtractor for JsxExtractor {
+    fn name(&self) -> &'static str {
+        "jsx"
+    }
+
+    fn extract(&self, source: &[u8], capacity_hint: usize) -> ExtractedClasses {
+        let mut scan = Scan::new(source, capacity_hint);
+        scan.attributes(&["className", "class"], |scan, value| match value {
+            AttrValue::Quoted(text) => scan.class_list(text),
+            AttrValue::Braced(expr) => scan.expression(expr),
+        });
+        scan.helper_calls();
+        scan.dx_attributes();
+        scan.finish()
+    }
+
+    fn element_class_lists(&self, source: &[u8]) -> Vec<(usize, Vec<String>)> {
+        let mut lists = Vec::new();
+        let mut scan = Scan::new(source, 0);
+        scan.attributes(&["className", "class"], |scan, value| {
+            if let AttrValue::Quoted(text) = value {
+                let classes = text.split_whitespace().map(str::to_string).collect();
+                lists.push((scan.offset_of(text), classes));
+            }
+        });
+        lists.sort_by_key(|(offset, _)| *offset);
+        lists
+    }
+}
+
+/// Vue SFCs: static `class`, bound `:class`/`v-bind:class` (object/array syntax) and helper calls.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct VueExtractor;
+
+impl ClassExtractor for VueExtractor {
+    fn name(&self) -> &'static str {
+        "vue"
+    }
+
+    fn extract(&self, source: &[u8], capacity_hint: usize) -> ExtractedClasses {
+        let mut scan = Scan::new(source, capacity_hint);
+        scan.attributes(&["class"], |scan, value| {
+            if let AttrValue::Quoted(text) = value {
+                scan.class_list(text);
+            }
+        });
+        scan.attributes(&[":class", "v-bind:class"], |scan, value| match value {
+            AttrValue::Quoted(expr) | AttrValue::Braced(expr) => scan.expression(expr),
+        });
+        scan.helper_calls();
+        scan.dx_attributes();
+        scan.finish()
+    }
+}
+
+/// Svelte: `class` with `{...}` interpolation, `class={...}`, `class:name={...}` directives and
+/// helper calls.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SvelteExtractor;
+
+impl ClassExtractor for SvelteExtractor {
+    fn name(&self) -> &'static str {
+        "svelte"
+    }
+
+    fn extract(&self, source: &[u8], capacity_hint: usize) -> ExtractedClasses {
+        let mut scan = Scan::new(source, capacity_hint);
+        scan.attributes(&["class"], |scan, value| match value {
+            AttrValue::Quoted(text) => scan.interpolated(text),
+            AttrValue::Braced(expr) => scan.expression(expr),
+        });
+        scan.directives("class:");
+        scan.helper_calls();
+        scan.dx_attributes();
+        scan.finish()
+    }
+}
+
+/// Astro: `class` with `{...}` interpolation, `class={...}`, `class:list={...}` and helper calls.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AstroExtractor;
+
+impl ClassExtractor for AstroExtractor {
+    fn name(&self) -> &'static str {
+        "astro"
+    }
+
+    fn extract(&self, source: &[u8], capacity_hint: usize) -> ExtractedClasses {
+        let mut scan = Scan::new(source, capacity_hint);
+        scan.attributes(&["class", "className"], |scan, value| match value {
+            AttrValue::Quoted(text) => scan.interpolated(text),
+            AttrValue::Braced(expr) => scan.expression(expr),
+        });
+        scan.attributes(&["class:list"], |scan, value| match value {
+            AttrValue::Quoted(expr) | AttrValue::Braced(expr) => scan.expression(expr),
+        });
+        scan.helper_calls();
+        scan.dx_attributes();
+        scan.finish()
+    }
+}
+
+/// Maps file extensions to extractors. Custom extractors can be registered on top of the
+/// built-in ones.
+#[derive(Clone)]
+pub struct ExtractorRegistry {
+    by_extension: AHashMap<String, Arc<dyn ClassExtractor>>,
+    fallback: Arc<dyn ClassExtractor>,
+}
+
+impl Default for ExtractorRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            by_extension: AHashMap::default(),
+            fallback: Arc::new(HtmlExtractor),
+        };
+        let jsx: Arc<dyn ClassExtractor> = Arc::new(JsxExtractor);
+        for ext in ["jsx", "tsx", "js", "ts", "mjs", "cjs", "mdx"] {
+            registry.by_extension.insert(ext.to_string(), jsx.clone());
+        }
+        registry.register("vue", VueExtractor);
+        registry.register("svelte", SvelteExtractor);
+        registry.register("astro", AstroExtractor);
+        registry
+    }
+}
+
+impl fmt::Debug for ExtractorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        for (ext, extractor) in &self.by_extension {
+            map.entry(ext, &extractor.name());
+        }
+        map.entry(&"*", &self.fallback.name());
+        map.finish()
+    }
+}
+
+impl ExtractorRegistry {
+    pub fn register<E: ClassExtractor + 'static>(&mut self, extension: &str, extractor: E) {
+        self.by_extension.insert(
+            extension.trim_start_matches('.').to_ascii_lowercase(),
+            Arc::new(extractor),
+        );
+    }
+
+    pub fn for_path(&self, path: &Path) -> &dyn ClassExtractor {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| self.by_extension.get(&e.to_ascii_lowercase()))
+            .unwrap_or(&self.fallback)
+            .as_ref()
+    }
+
+    pub fn extract(&self, path: &Path, source: &[u8], capacity_hint: usize) -> ExtractedClasses {
+        self.for_path(path).extract(source, capacity_hint)
+    }
+}
+
+enum AttrValue<'a> {
+    Quoted(&'a str),
+    Braced(&'a str),
+}
+
+struct Scan<'a> {
+    src: &'a [u8],
+    set: AHashSet<String>,
+    collector: GroupCollector,
+}
+
+impl<'a> Scan<'a> {
+    fn new(src: &'a [u8], capacity_hint: usize) -> Self {
+        Self {
+            src,
+            set: AHashSet::with_capacity(capacity_hint.max(64)),
+            collector: GroupCollector::default(),
+        }
+    }
+
+    fn finish(self) -> ExtractedClasses {
+        ExtractedClasses {
+            classes: self.set,
+            group_events: self.collector.into_events(),
+        }
+    }
+
+    /// Byte offset of `text`, a slice of the source, from the start of the source.
+    fn offset_of(&self, text: &str) -> usize {
+        text.as_ptr() as usize - self.src.as_ptr() as usize
+    }
+
+    fn class_list(&mut self, text: &str) {
+        expand_grouping_into(text, &mut self.set, &mut self.collector);
+    }
+
+    /// A class list that may contain `{expression}` holes (Svelte/Astro).
+    fn interpolated(&mut self, text: &str) {
+        let bytes = text.as_bytes();
+        let mut literal = String::with_capacity(text.len());
+        let mut i = 0usize;
+        let mut last = 0usize;
+        while i < bytes.len() {
+            if bytes[i] == b'{' {
+                let Some(end) = matching_close(bytes, i) else {
+                    break;
+                };
+                literal.push_str(&text[last..i]);
+                literal.push(HOLE);
+                self.expression(&text[i + 1..end]);
+                i = end + 1;
+                last = i;
+            } else {
+                i += 1;
+            }
+        }
+        literal.push_str(&text[last..]);
+        self.literal(&literal);
+    }
+
+    /// Static text of a literal; tokens glued to an interpolation hole are incomplete and skipped.
+    fn literal(&mut self, text: &str) {
+        if !text.contains(HOLE) {
+            self.class_list(text);
+            return;
+        }
+        let cleaned = text
+            .split_whitespace()
+            .filter(|t| !t.contains(HOLE))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.class_list(&cleaned);
+    }
+
+    /// Collects classes from a JS expression: every string/template literal is a class list, and
+    /// bare object keys (`{ active: isActive }`) are class names.
+    fn expression(&mut self, expr: &str) {
+        let bytes = expr.as_bytes();
+        let mut i = 0usize;
+        let mut braces: Vec<u8> = Vec::new();
+        let mut prev = 0u8;
+        while i < bytes.len() {
+            let b = bytes[i];
+            match b {
+                b'"' | b'\'' => {
+                    let end = skip_string(bytes, i);
+                    let inner_end = if end > i + 1 && bytes[end - 1] == b {
+                        end - 1
+                    } else {
+                        end
+                    };
+                    self.class_list(&expr[i + 1..inner_end]);
+                    i = end;
+                    prev = b;
+                    continue;
+                }
+                b'`' => {
+                    let end = self.template(expr, i);
+                    i = end;
+                    prev = b;
+                    continue;
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                    i = memchr::memchr(b'\n', &bytes[i..]).map_or(bytes.len(), |o| i + o);
+                    continue;
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                    i = memchr::memmem::find(&bytes[i + 2..], b"*/")
+                        .map_or(bytes.len(), |o| i + o + 4);
+                    continue;
+                }
+                b'{' | b'[' | b'(' => braces.push(b),
+                b'}' | b']' | b')' => {
+                    braces.pop();
+                }
+                _ if is_ident_start(b)
+                    && braces.last() == Some(&b'{')
+                    && matches!(prev, b'{' | b',') =>
+                {
+                    let start = i;
+                    while i < bytes.len() && is_ident_byte(bytes[i]) {
+                        i += 1;
+                    }
+                    let mut j = i;
+                    while j < bytes.len() && bytes[j].is_ascii_whitespace() {
+                        j += 1;
+                    }
+                    if bytes.get(j) == Some(&b':') && bytes.get(j + 1) != Some(&b':') {
+                        self.set.insert(expr[start..i].to_string());
+                    }
+                    prev = b'a';
+                    continue;
+                }
+                _ => {}
+            }
+            if !b.is_ascii_whitespace() {
+                prev = b;
+            }
+            i += 1;
+        }
+    }
+
+    /// Handles a template literal starting at `start`; returns the index after the closing
+    /// backtick.
+    fn template(&mut self, expr: &str, start: usize) -> usize {
+        let bytes = expr.as_bytes();
+        let mut literal = String::new();
+        let mut i = start + 1;
+        let mut last = i;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 2,
+                b'`' => break,
+                b'$' if bytes.get(i + 1) == Some(&b'{') => {
+                    let Some(end) = matching_close(bytes, i + 1) else {
+                        break;
+                    };
+                    literal.push_str(&expr[last..i]);
+                    literal.push(HOLE);
+                    self.expression(&expr[i + 2..end]);
+                    i = end + 1;
+                    last = i;
+                }
+                _ => i += 1,
+            }
+        }
+        let end = i.min(bytes.len());
+        literal.push_str(&expr[last.min(end)..end]);
+        self.literal(&literal);
+        (end + 1).min(bytes.len())
+    }
+
+    /// Visits every `name=` attribute in `names`, with quoted or `{...}` values.
+    fn attributes(&mut self, names: &[&str], mut visit: impl FnMut(&mut Self, AttrValue<'a>)) {
+        let src = self.src;
+        for name in names {
+            let finder = memchr::memmem::Finder::new(name.as_bytes());
+            let mut pos = 0usize;
+            while let Some(idx) = finder.find(&src[pos..]) {
+                let at = pos + idx;
+                pos = at + name.len();
+                if at > 0 && is_attr_byte(src[at - 1]) {
+                    continue;
+                }
+                let mut i = skip_ws(src, at + name.len());
+                if src.get(i) != Some(&b'=') {
+                    continue;
+                }
+                i = skip_ws(src, i + 1);
+                let Some(&open) = src.get(i) else {
+                    break;
+                };
+                let value = match open {
+                    b'"' | b'\'' => match memchr::memchr(open, &src[i + 1..]) {
+                        Some(off) => {
+                            pos = i + 1 + off + 1;
+                            str_at(src, i + 1, i + 1 + off).map(AttrValue::Quoted)
+                        }
+                        None => break,
+                    },
+                    b'{' => match matching_close(src, i) {
+                        Some(end) => {
+                            pos = end + 1;
+                            str_at(src, i + 1, end).map(AttrValue::Braced)
+                        }
+                        None => break,
+                    },
+                    _ => None,
+                };
+                if let Some(value) = value {
+                    visit(self, value);
+                }
+            }
+        }
+    }
+
+    /// Svelte `class:name` / `class:name={cond}` directives; the directive name is the class.
+    fn directives(&mut self, prefix: &str) {
+        let src = self.src;
+        let finder = memchr::memmem::Finder::new(prefix.as_bytes());
+        let mut pos = 0usize;
+        while let Some(idx) = finder.find(&src[pos..]) {
+            let at = pos + idx;
+            pos = at + prefix.len();
+            if at > 0 && is_attr_byte(src[at - 1]) {
+                continue;
+            }
+            let start = pos;
+            let mut end = start;
+            while end < src.len()
+                && !src[end].is_ascii_whitespace()
+                && !matches!(src[end], b'=' | b'>' | b'/' | b'"' | b'\'' | b'{')
+            {
+                end += 1;
+            }
+            if let Some(name) = str_at(src, start, end)
+                && !name.is_empty()
+            {
+                self.set.insert(name.to_string());
+            }
+            pos = end;
+        }
+    }
+
+    /// String arguments of `clsx(...)`, `cn(...)` and friends anywhere in the file.
+    fn helper_calls(&mut self) {
+        let src = self.src;
+        for helper in CLASS_HELPERS {
+            let finder = memchr::memmem::Finder::new(helper.as_bytes());
+            let mut pos = 0usize;
+            while let Some(idx) = finder.find(&src[pos..]) {
+                let at = pos + idx;
+                pos = at + helper.len();
+                if at > 0 && (is_ident_byte(src[at - 1]) || src[at - 1] == b'.') {
+                    continue;
+                }
+                let open = skip_ws(src, pos);
+                if src.get(open) != Some(&b'(') {
+                    continue;
+                }
+                let Some(end) = matching_close(src, open) else {
+                    break;
+                };
+                if let Some(args) = str_at(src, open + 1, end) {
+                    self.expression(args);
+                }
+                pos = end + 1;
+            }
+        }
+    }
+
+    fn dx_attributes(&mut self) {
+        super::extract_dx_attributes(self.src, &mut self.set, &mut self.collector);
+    }
+}
+
+#[inline]
+fn str_at(src: &[u8], start: usize, end: usize) -> Option<&str> {
+    std::str::from_utf8(src.get(start..end)?).ok()
+}
+
+#[inline]
+fn skip_ws(src: &[u8], mut i: usize) -> usize {
+    while i < src.len() && src[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+#[inline]
+fn is_ident_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || b == b'$'
+}
+
+#[inline]
+fn is_ident_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b == b'-'
+}
+
+#[inline]
+fn is_attr_byte(b: u8) -> bool {
+    is_ident_byte(b) || b == b':' || b == b'.'
+}
+
+/// Returns the index just past a `'`/`"` string starting at `start`.
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Finds the bracket closing the one at `open`, skipping over string and template literals.
+fn matching_close(bytes: &[u8], open: usize) -> Option<usize> {
+    let (open_b, close_b) = match bytes.get(open)? {
+        b'{' => (b'{', b'}'),
+        b'(' => (b'(', b')'),
+        b'[' => (b'[', b']'),
+        _ => return None,
+    };
+    let mut depth = 0usize;
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' | b'`' => {
+                i = skip_string(bytes, i);
+                continue;
+            }
+            b if b == open_b => depth += 1,
+            b if b == close_b => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classes(extractor: &dyn ClassExtractor, src: &str) -> Vec<String> {
+        let mut out: Vec<String> = extractor
+            .extract(src.as_bytes(), 0)
+            .classes
+            .into_iter()
+            .collect();
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn jsx_class_name_forms() {
+        let src = r#"
+            const a = <div className="flex p-4" />;
+            const b = <div className={"m-2"} />;
+            const c = <div className={`grid ${open ? "gap-2" : 'gap-4'} w-${size}`} />;
+            const d = <div className={cn("text-sm", { "font-bold": active, underline: hover })} />;
+            const e = clsx('rounded', cond && 'shadow');
+        "#;
+        assert_eq!(
+            classes(&JsxExtractor, src),
+            vec![
+                "flex",
+                "font-bold",
+                "gap-2",
+                "gap-4",
+                "grid",
+                "m-2",
+                "p-4",
+                "rounded",
+                "shadow",
+                "text-sm",
+                "underline"
+            ]
+        );
+    }
+
+    #[test]
+    fn jsx_element_class_lists_keep_literal_attributes() {
+        let src =
+            "<p className=\"bg-muted text-ink\" />\n<div className={cn(\"flex\")} class='p-2' />";
+        assert_eq!(
+            JsxExtractor.element_class_lists(src.as_bytes()),
+            vec![
+                (14, vec!["bg-muted".to_string(), "text-ink".to_string()]),
+                (src.find("p-2").unwrap(), vec!["p-2".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn vue_bound_class_object_and_array() {
+        let src = r#"<div class="card" :class="{ 'is-active': active, hidden: !shown }"></div>
+            <span v-bind:class="[big ? 'text-lg' : 'text-sm', 'px-2']"></span>"#;
+        assert_eq!(
+            classes(&VueExtractor, src),
+            vec!["card", "hidden", "is-active", "px-2", "text-lg", "text-sm"]
+        );
+    }
+
+    #[test]
+    fn svelte_directives_and_interpolation() {
+        let src = r#"<button class="btn {primary ? 'bg-blue' : ''} p-{n}" class:active={on} class:disabled></button>"#;
+        assert_eq!(
+            classes(&SvelteExtractor, src),
+            vec!["active", "bg-blue", "btn", "disabled"]
+        );
+    }
+
+    #[test]
+    fn astro_class_list() {
+        let src = r#"<div class:list={["box", { red: isRed }]} class="m-1"></div>"#;
+        assert_eq!(classes(&AstroExtractor, src), vec!["box", "m-1", "red"]);
+    }
+
+    #[test]
+    fn registry_picks_extractor_by_extension() {
+        let registry = ExtractorRegistry::default();
+        assert_eq!(registry.for_path(Path::new("src/App.tsx")).name(), "jsx");
+        assert_eq!(registry.for_path(Path::new("Card.VUE")).name(), "vue");
+        assert_eq!(registry.for_path(Path::new("index.html")).name(), "html");
+        assert_eq!(registry.for_path(Path::new("README")).name(), "html");
+    }
+}
@@ -43,6 +43,8 @@ pub struct IncrementalParser {
     regions: Vec<RegionCache>,
     /// Statistics
     pub stats: IncrementalStats,
+    /// When false every call is a full parse (`[parser] incremental = false`)
+    enabled: bool,
 }
 
 /// Statistics for incremental parsing
@@ -69,17 +71,22 @@ impl IncrementalParser {
             prev_content: Vec::new(),
             regions: Vec::new(),
             stats: IncrementalStats::default(),
+            enabled: true,
         }
     }
 
+    /// Enable or disable incremental parsing
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
     /// Parse HTML incrementally, only re-parsing changed sections
     pub fn parse_incremental(
         &mut self,
         html_bytes: &[u8],
         capacity_hint: usize,
     ) -> ExtractedClasses {
-        // Check if incremental parsing is disabled via environment variable
-        if std::env::var("DX_DISABLE_INCREMENTAL").ok().as_deref() == Some("1") {
+        if !self.enabled {
             self.stats.full_parses += 1;
             self.stats.bytes_parsed += html_bytes.len();
             let result = extract_classes_fast(html_bytes, capacity_hint);
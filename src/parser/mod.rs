@@ -4,10 +4,12 @@ use smallvec::SmallVec;
 use std::hash::Hasher;
 use std::ops::Range;
 
+pub mod extractors;
 pub mod incremental;
 pub mod optimized;
 
 // Re-export incremental parser
+pub use extractors::ExtractorRegistry;
 pub use incremental::IncrementalParser;
 
 #[derive(Debug, Clone)]
@@ -112,17 +114,28 @@ fn sanitize_group_token(raw: &str) -> &str {
     }
     raw
 }
+/// Position of the first byte matching `pred` outside `[...]` arbitrary values.
+#[inline]
+fn find_outside_brackets(bytes: &[u8], pred: impl Fn(u8) -> bool) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'[' => depth += 1,
+            b']' => depth = depth.saturating_sub(1),
+            _ if depth == 0 && pred(b) => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
 #[inline]
 fn expand_grouping_into(s: &str, out: &mut AHashSet<String>, collector: &mut GroupCollector) {
-    let s = match s.as_bytes().iter().position(|&b| b == b'#') {
+    let s = match find_outside_brackets(s.as_bytes(), |b| b == b'#') {
         Some(i) => &s[..i],
         None => s,
     };
-    if !s
-        .as_bytes()
-        .iter()
-        .any(|&b| matches!(b, b'(' | b')' | b'+'))
-    {
+    if find_outside_brackets(s.as_bytes(), |b| matches!(b, b'(' | b')' | b'+')).is_none() {
         fast_split_whitespace_insert(s, out);
         return;
     }
@@ -197,7 +210,16 @@ fn expand_grouping_into(s: &str, out: &mut AHashSet<String>, collector: &mut Gro
             tok_start = Some(i);
         }
 
-        while i < n && !matches!(bytes[i], b' ' | b'\n' | b'\r' | b'\t' | b'(' | b')') {
+        // Parentheses inside `[...]` belong to an arbitrary value, e.g. `w-[calc(100%-2rem)]`.
+        let mut depth = 0usize;
+        while i < n {
+            match bytes[i] {
+                b' ' | b'\n' | b'\r' | b'\t' => break,
+                b'(' | b')' if depth == 0 => break,
+                b'[' => depth += 1,
+                b']' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
             i += 1;
         }
 
@@ -277,12 +299,43 @@ fn expand_grouping_into(s: &str, out: &mut AHashSet<String>, collector: &mut Gro
 pub fn extract_classes_fast(html_bytes: &[u8], capacity_hint: usize) -> ExtractedClasses {
     let mut set = AHashSet::with_capacity(capacity_hint.max(64));
     let mut collector = GroupCollector::default();
+    for_each_class_value(html_bytes, |_, value| {
+        expand_grouping_into(value, &mut set, &mut collector);
+    });
+
+    extract_dx_attributes(html_bytes, &mut set, &mut collector);
+
+    ExtractedClasses {
+        classes: set,
+        group_events: collector.into_events(),
+    }
+}
+
+/// The class list of every `class="..."` attribute, with the byte offset of its value.
+pub fn element_class_lists(html_bytes: &[u8]) -> Vec<(usize, Vec<String>)> {
+    let mut lists = Vec::new();
+    for_each_class_value(html_bytes, |offset, value| {
+        lists.push((
+            offset,
+            value.split_whitespace().map(str::to_string).collect(),
+        ));
+    });
+    lists
+}
+
+/// Calls `f` with the offset and text of each quoted `class` attribute value.
+fn for_each_class_value(html_bytes: &[u8], mut f: impl FnMut(usize, &str)) {
     let mut pos = 0usize;
     let n = html_bytes.len();
 
     let class_finder = Finder::new(b"class");
     while let Some(idx) = class_finder.find(&html_bytes[pos..]) {
         let start = pos + idx + 5;
+        // `:class="..."` is a framework binding holding an expression, not a class list.
+        if pos + idx > 0 && html_bytes[pos + idx - 1] == b':' {
+            pos = start;
+            continue;
+        }
         let mut i = start;
         while i < n && matches!(html_bytes[i], b' ' | b'\n' | b'\r' | b'\t') {
             i += 1;
@@ -311,12 +364,19 @@ pub fn extract_classes_fast(html_bytes: &[u8], capacity_hint: usize) -> Extracte
             None => break,
         };
         if let Ok(value_str) = std::str::from_utf8(&html_bytes[value_start..value_end]) {
-            expand_grouping_into(value_str, &mut set, &mut collector);
+            f(value_start, value_str);
         }
         pos = value_end + 1;
     }
+}
 
-    pos = 0;
+fn extract_dx_attributes(
+    html_bytes: &[u8],
+    set: &mut AHashSet<String>,
+    collector: &mut GroupCollector,
+) {
+    let n = html_bytes.len();
+    let mut pos = 0usize;
     let dx_finder = Finder::new(b"dx-");
     while let Some(idx) = dx_finder.find(&html_bytes[pos..]) {
         let mut i = pos + idx;
@@ -356,15 +416,10 @@ pub fn extract_classes_fast(html_bytes: &[u8], capacity_hint: usize) -> Extracte
             None => break,
         };
         if let Ok(value_str) = std::str::from_utf8(&html_bytes[value_start..value_end]) {
-            expand_grouping_into(value_str, &mut set, &mut collector);
+            expand_grouping_into(value_str, set, collector);
         }
         pos = value_end + 1;
     }
-
-    ExtractedClasses {
-        classes: set,
-        group_events: collector.into_events(),
-    }
 }
 
 #[derive(Debug)]
@@ -1075,6 +1130,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn arbitrary_values_survive_extraction() {
+        let html = br#"<div class="bg-[#1e293b] w-[calc(100%-2rem)] card(p-[3px])"></div>"#;
+        let extracted = extract_classes_fast(html, 0);
+        assert!(extracted.classes.contains("bg-[#1e293b]"));
+        assert!(extracted.classes.contains("w-[calc(100%-2rem)]"));
+        assert!(extracted.classes.contains("card:p-[3px]"));
+    }
+
+    #[test]
+    fn element_class_lists_keep_each_attribute_apart() {
+        let html = b"<div class=\"bg-white text-black\">\n<p :class=\"x\" class='p-4'></p></div>";
+        let lists = element_class_lists(html);
+        assert_eq!(lists.len(), 2);
+        assert_eq!(
+            lists[0],
+            (12, vec!["bg-white".to_string(), "text-black".to_string()])
+        );
+        assert_eq!(lists[1].1, vec!["p-4".to_string()]);
+    }
+
     #[test]
     fn rewrite_duplicates_into_group_alias() {
         let html = br#"<h1 class="border flex text-red-500" dx-group="old(alias)">Hello</h1>
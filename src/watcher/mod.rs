@@ -1,67 +1,141 @@
-use crate::core::{AppState, rebuild_styles};
+use crate::core::diagnostics::Reporter;
+use crate::core::{AppState, RebuildOptions, rebuild_styles};
 use colored::Colorize;
 use notify::RecursiveMode;
 use notify::{Event, Watcher};
 use notify_debouncer_full::new_debouncer;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, mpsc};
 use std::time::{Duration, Instant};
 
 use crate::config::Config;
+use crate::datasource::glob::{GlobSet, relative_key};
+
+const CONTENT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+fn content_roots(content: &GlobSet, html_dir: &Path) -> Vec<PathBuf> {
+    content
+        .base_dirs()
+        .into_iter()
+        .filter(|dir| dir.is_dir() && !dir.starts_with(html_dir))
+        .collect()
+}
+
+/// Brings the content index up to date with a batch of watcher events. Reported content files
+/// are re-read on their own; the globs are only walked again (without the lock) when a directory
+/// under a content root appeared or went away, since its files produce no events of their own.
+/// Returns true when any content file changed.
+fn sync_content(state: &Mutex<AppState>, content: &GlobSet, events: &[&Event]) -> bool {
+    use notify::EventKind;
+    use notify::event::ModifyKind;
+    if content.is_empty() {
+        return false;
+    }
+    let roots: Vec<String> = content
+        .base_dirs()
+        .iter()
+        .map(|d| relative_key(d))
+        .collect();
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut walk = false;
+    for event in events {
+        let structural = matches!(
+            event.kind,
+            EventKind::Any
+                | EventKind::Create(_)
+                | EventKind::Remove(_)
+                | EventKind::Modify(ModifyKind::Name(_))
+        );
+        for path in &event.paths {
+            if content.matches(path) {
+                paths.push(path.clone());
+            } else if structural && (path.is_dir() || !path.exists()) {
+                let key = relative_key(path);
+                walk |= roots.iter().any(|root| key.starts_with(root.as_str()));
+            }
+        }
+    }
+    if walk {
+        let walked = content.walk();
+        return state.lock().unwrap().content.refresh(walked);
+    }
+    !paths.is_empty() && state.lock().unwrap().content.update(&paths)
+}
 
 pub fn start(
     state: Arc<Mutex<AppState>>,
     config: Config,
+    mut reporter: Reporter,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    if let Ok(poll_ms_str) = std::env::var("DX_WATCH_POLL_MS") {
-        if let Ok(interval_ms) = poll_ms_str.parse::<u64>() {
-            let interval = Duration::from_millis(interval_ms.max(1));
-            use std::fs;
-            let mut last_mtime = fs::metadata(&config.paths.index_file)
-                .and_then(|m| m.modified())
-                .ok();
-            loop {
-                std::thread::sleep(interval);
-                if let Ok(meta) = fs::metadata(&config.paths.index_file) {
-                    if let Ok(modified) = meta.modified() {
-                        if last_mtime.map(|t| t != modified).unwrap_or(true) {
-                            last_mtime = Some(modified);
-                            if let Err(e) =
-                                rebuild_styles(state.clone(), &config.paths.index_file, false)
-                            {
-                                eprintln!("{} {}", "Error rebuilding styles:".red(), e);
-                            }
-                        }
+    let content = GlobSet::new(&config.content);
+    let html_dir = Path::new(&config.paths.html_dir);
+    let options = RebuildOptions::from_config(&config);
+    if let Some(interval_ms) = config.watch_poll_ms() {
+        let interval = Duration::from_millis(interval_ms.max(1));
+        use std::fs;
+        let mut last_mtime = fs::metadata(&config.paths.index_file)
+            .and_then(|m| m.modified())
+            .ok();
+        // Walking the content globs is far more expensive than a stat of the index file.
+        let content_interval = interval.max(CONTENT_POLL_INTERVAL);
+        let mut last_walk = Instant::now();
+        loop {
+            std::thread::sleep(interval);
+            let mut changed = false;
+            if let Ok(meta) = fs::metadata(&config.paths.index_file) {
+                if let Ok(modified) = meta.modified() {
+                    if last_mtime.map(|t| t != modified).unwrap_or(true) {
+                        last_mtime = Some(modified);
+                        changed = true;
                     }
                 }
             }
+            if !content.is_empty() && last_walk.elapsed() >= content_interval {
+                last_walk = Instant::now();
+                let walked = content.walk();
+                changed |= state.lock().unwrap().content.refresh(walked);
+            }
+            if changed {
+                match rebuild_styles(state.clone(), &config.paths.index_file, false, &options) {
+                    Ok(diagnostics) => reporter.report(&diagnostics),
+                    Err(e) => eprintln!("{} {}", "Error rebuilding styles:".red(), e),
+                }
+            }
         }
     }
 
-    if std::env::var("DX_WATCH_RAW").ok().as_deref() == Some("1") {
+    if config.watch_raw() {
         let (tx, rx) = mpsc::channel::<Result<Event, notify::Error>>();
         let mut watcher = notify::recommended_watcher(move |res| {
             let _ = tx.send(res);
         })?;
-        watcher.watch(Path::new(&config.paths.html_dir), RecursiveMode::Recursive)?;
+        watcher.watch(html_dir, RecursiveMode::Recursive)?;
+        for root in content_roots(&content, html_dir) {
+            watcher.watch(&root, RecursiveMode::Recursive)?;
+        }
         let mut last_trigger = Instant::now() - Duration::from_secs(1);
         let min_gap = Duration::from_millis(5);
         loop {
             match rx.recv() {
                 Ok(Ok(event)) => {
-                    let relevant = event.paths.iter().any(|p| {
+                    let mut relevant = event.paths.iter().any(|p| {
                         if let Some(s) = p.to_str() {
                             s.ends_with("index.html")
                         } else {
                             false
                         }
                     });
+                    relevant |= sync_content(&state, &content, &[&event]);
                     if relevant && last_trigger.elapsed() >= min_gap {
                         last_trigger = Instant::now();
-                        if let Err(e) =
-                            rebuild_styles(state.clone(), &config.paths.index_file, false)
-                        {
-                            eprintln!("{} {}", "Error rebuilding styles:".red(), e);
+                        match rebuild_styles(
+                            state.clone(),
+                            &config.paths.index_file,
+                            false,
+                            &options,
+                        ) {
+                            Ok(diagnostics) => reporter.report(&diagnostics),
+                            Err(e) => eprintln!("{} {}", "Error rebuilding styles:".red(), e),
                         }
                     }
                 }
@@ -73,21 +147,24 @@ pub fn start(
     }
 
     let (tx, rx) = mpsc::channel();
-    let debounce_ms = std::env::var("DX_DEBOUNCE_MS")
-        .ok()
-        .and_then(|v| v.parse::<u64>().ok())
-        .or_else(|| config.watch.as_ref().and_then(|w| w.debounce_ms))
+    let debounce_ms = config
+        .watch
+        .as_ref()
+        .and_then(|w| w.debounce_ms)
         .unwrap_or(250);
 
     let mut debouncer = new_debouncer(Duration::from_millis(debounce_ms.max(1)), None, tx)?;
-    debouncer.watch(Path::new(&config.paths.html_dir), RecursiveMode::Recursive)?;
+    debouncer.watch(html_dir, RecursiveMode::Recursive)?;
+    for root in content_roots(&content, html_dir) {
+        debouncer.watch(&root, RecursiveMode::Recursive)?;
+    }
 
     loop {
         let res = rx.recv();
         match res {
             Ok(Ok(events)) => {
                 let mut relevant = false;
-                for ev in events {
+                for ev in &events {
                     for path in &ev.paths {
                         if let Some(p) = path.to_str() {
                             if p.ends_with("index.html") || p.ends_with("style.css") {
@@ -100,9 +177,12 @@ pub fn start(
                         break;
                     }
                 }
+                let events: Vec<&Event> = events.iter().map(|ev| &ev.event).collect();
+                relevant |= sync_content(&state, &content, &events);
                 if relevant {
-                    if let Err(e) = rebuild_styles(state.clone(), &config.paths.index_file, false) {
-                        eprintln!("{} {}", "Error rebuilding styles:".red(), e);
+                    match rebuild_styles(state.clone(), &config.paths.index_file, false, &options) {
+                        Ok(diagnostics) => reporter.report(&diagnostics),
+                        Err(e) => eprintln!("{} {}", "Error rebuilding styles:".red(), e),
                     }
                 }
             }